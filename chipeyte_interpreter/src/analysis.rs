@@ -0,0 +1,616 @@
+use crate::cpu::{instruction_decoder::decode, INSTRUCTION_LENGTH};
+use crate::operations::{Category, Ops};
+use crate::quirks::Variant;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// How control gets from one instruction to another.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum EdgeKind {
+    /// On to the next instruction.
+    FallThrough,
+    /// Over the next instruction, when a condition holds.
+    Skip,
+    Jump,
+    Call,
+    /// Back to the instruction after a call.
+    Return,
+    /// To an address only known at run time, from an indirect jump.
+    Unresolved,
+}
+
+/// The result of statically analysing a program, following every path from its entry point.
+///
+/// Indirect jumps (`JP V0`) cannot be followed, so code only reachable through them counts as
+/// data.
+#[derive(Debug, PartialEq)]
+pub struct Analysis {
+    /// Address the program is loaded at and starts from.
+    pub origin: u16,
+    pub size: usize,
+    /// Reachable instructions by address.
+    pub code: BTreeMap<u16, Ops>,
+    /// Entry points of the subroutines called.
+    pub subroutines: BTreeSet<u16>,
+    /// The deepest nesting of calls, or `None` if subroutines call themselves.
+    pub max_call_depth: Option<usize>,
+    /// Jumps and calls to addresses outside the program, as (from, to).
+    pub external_targets: Vec<(u16, u16)>,
+    /// Addresses of `SYS` calls to machine code, which are ignored.
+    pub sys_calls: Vec<u16>,
+    /// Addresses of indirect jumps.
+    pub indirect_jumps: Vec<u16>,
+}
+
+impl Analysis {
+    pub fn of(program: &[u8], origin: u16) -> Analysis {
+        let mut analysis = Analysis {
+            origin,
+            size: program.len(),
+            code: BTreeMap::new(),
+            subroutines: BTreeSet::new(),
+            max_call_depth: None,
+            external_targets: Vec::new(),
+            sys_calls: Vec::new(),
+            indirect_jumps: Vec::new(),
+        };
+        let mut pending = vec![origin];
+
+        while let Some(address) = pending.pop() {
+            if analysis.code.contains_key(&address) {
+                continue;
+            }
+
+            let op = match analysis.fetch(program, address) {
+                Some(op) => op,
+                None => continue,
+            };
+            analysis.code.insert(address, op);
+
+            match op {
+                Ops::SYS(_) => analysis.sys_calls.push(address),
+                Ops::JPV0(_) => analysis.indirect_jumps.push(address),
+                Ops::CALL(target) => {
+                    analysis.subroutines.insert(target);
+                }
+                _ => {}
+            }
+
+            for (target, kind) in successors(program, origin, address, &op) {
+                let transfer = kind == EdgeKind::Jump || kind == EdgeKind::Call;
+
+                if transfer && !analysis.contains(target) {
+                    analysis.external_targets.push((address, target));
+                    continue;
+                }
+
+                pending.push(target);
+            }
+        }
+
+        let code = &analysis.code;
+        analysis
+            .subroutines
+            .retain(|entry| code.contains_key(entry));
+        analysis.max_call_depth = analysis.call_depth(program);
+        analysis.external_targets.sort_unstable();
+        analysis.sys_calls.sort_unstable();
+        analysis.indirect_jumps.sort_unstable();
+        analysis
+    }
+
+    /// Whether `address` lies within the program.
+    pub fn contains(&self, address: u16) -> bool {
+        address >= self.origin && ((address - self.origin) as usize) < self.size
+    }
+
+    /// Addresses of the bytes making up reachable instructions.
+    pub fn code_bytes(&self) -> BTreeSet<u16> {
+        self.code
+            .iter()
+            .flat_map(|(address, op)| *address..*address + length(op))
+            .filter(|address| self.contains(*address))
+            .collect()
+    }
+
+    /// Runs of reachable code, as (start, end) with `end` exclusive.
+    pub fn code_regions(&self) -> Vec<(u16, u16)> {
+        let code = self.code_bytes();
+
+        regions(self.origin, self.size, |address| code.contains(&address))
+    }
+
+    /// Runs of bytes that are not reachable code, as (start, end) with `end` exclusive.
+    pub fn data_regions(&self) -> Vec<(u16, u16)> {
+        let code = self.code_bytes();
+
+        regions(self.origin, self.size, |address| !code.contains(&address))
+    }
+
+    /// Number of reachable instructions in each category.
+    pub fn categories(&self) -> BTreeMap<Category, usize> {
+        let mut counts = BTreeMap::new();
+
+        for op in self.code.values() {
+            *counts.entry(op.category()).or_insert(0) += 1;
+        }
+
+        counts
+    }
+
+    /// The first variant that has every reachable instruction.
+    pub fn variant(&self) -> Variant {
+        self.code
+            .values()
+            .map(|op| op.variant())
+            .max()
+            .unwrap_or(Variant::Chip8)
+    }
+
+    fn fetch(&self, program: &[u8], address: u16) -> Option<Ops> {
+        if !self.contains(address) || !self.contains(address + 1) {
+            return None;
+        }
+
+        let offset = (address - self.origin) as usize;
+        let opcode = u16::from_be_bytes([program[offset], program[offset + 1]]);
+
+        // The CPU halts on 0000
+        if opcode == 0 {
+            return None;
+        }
+
+        Some(decode(opcode))
+    }
+
+    /// The deepest nesting of calls from the entry point, following each subroutine to its
+    /// returns without entering the subroutines it calls.
+    fn call_depth(&self, program: &[u8]) -> Option<usize> {
+        let mut callees = HashMap::new();
+
+        for entry in Some(self.origin)
+            .into_iter()
+            .chain(self.subroutines.iter().copied())
+        {
+            callees.insert(entry, self.callees(program, entry));
+        }
+
+        depth(
+            self.origin,
+            &callees,
+            &mut HashMap::new(),
+            &mut BTreeSet::new(),
+        )
+    }
+
+    fn callees(&self, program: &[u8], entry: u16) -> BTreeSet<u16> {
+        let mut called = BTreeSet::new();
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![entry];
+
+        while let Some(address) = pending.pop() {
+            if !visited.insert(address) {
+                continue;
+            }
+
+            let op = match self.code.get(&address) {
+                Some(op) => op,
+                None => continue,
+            };
+
+            for (target, kind) in successors(program, self.origin, address, op) {
+                match kind {
+                    EdgeKind::Call if self.subroutines.contains(&target) => {
+                        called.insert(target);
+                    }
+                    EdgeKind::Call => {}
+                    _ => pending.push(target),
+                }
+            }
+        }
+
+        called
+    }
+}
+
+/// A run of instructions that control enters only at the first and leaves only after the last.
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    pub start: u16,
+    /// The instructions in order, as (address, operation).
+    pub instructions: Vec<(u16, Ops)>,
+}
+
+impl Block {
+    /// Address just past the last instruction.
+    pub fn end(&self) -> u16 {
+        self.instructions
+            .last()
+            .map_or(self.start, |(address, op)| address + length(op))
+    }
+}
+
+/// A control transfer between the blocks starting at `from` and `to`.
+///
+/// `to` is `None` for unresolved edges. Jumps and calls outside the program lead to an address
+/// that starts no block.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct Edge {
+    pub from: u16,
+    pub to: Option<u16>,
+    pub kind: EdgeKind,
+}
+
+/// The control flow graph of the reachable code.
+#[derive(Debug, PartialEq)]
+pub struct Graph {
+    /// Basic blocks by start address.
+    pub blocks: BTreeMap<u16, Block>,
+    pub edges: Vec<Edge>,
+}
+
+impl Graph {
+    /// Splits the reachable code into basic blocks and connects them. The blocks ending in `RET`
+    /// of a subroutine return to every block following a call to it.
+    pub fn of(program: &[u8], analysis: &Analysis) -> Graph {
+        let origin = analysis.origin;
+        let straight = |address: u16, op: &Ops| {
+            successors(program, origin, address, op)
+                == [(address + length(op), EdgeKind::FallThrough)]
+        };
+
+        let mut leaders: BTreeSet<u16> = analysis.subroutines.iter().copied().collect();
+        leaders.insert(origin);
+
+        for (address, op) in &analysis.code {
+            if !straight(*address, op) {
+                leaders.extend(
+                    successors(program, origin, *address, op)
+                        .into_iter()
+                        .map(|(target, _)| target),
+                );
+            }
+        }
+
+        let mut blocks = BTreeMap::new();
+
+        for start in leaders
+            .iter()
+            .filter(|start| analysis.code.contains_key(start))
+        {
+            let mut instructions = Vec::new();
+            let mut address = *start;
+
+            while let Some(op) = analysis.code.get(&address) {
+                instructions.push((address, *op));
+                address += length(op);
+
+                if !straight(address - length(op), op) || leaders.contains(&address) {
+                    break;
+                }
+            }
+
+            blocks.insert(
+                *start,
+                Block {
+                    start: *start,
+                    instructions,
+                },
+            );
+        }
+
+        let mut edges = BTreeSet::new();
+
+        for block in blocks.values() {
+            let (address, op) = block.instructions[block.instructions.len() - 1];
+
+            if let Ops::JPV0(_) = op {
+                edges.insert(Edge {
+                    from: block.start,
+                    to: None,
+                    kind: EdgeKind::Unresolved,
+                });
+            }
+
+            for (target, kind) in successors(program, origin, address, &op) {
+                let transfer = kind == EdgeKind::Jump || kind == EdgeKind::Call;
+
+                if transfer || blocks.contains_key(&target) {
+                    edges.insert(Edge {
+                        from: block.start,
+                        to: Some(target),
+                        kind,
+                    });
+                }
+            }
+        }
+
+        let mut graph = Graph {
+            blocks,
+            edges: edges.iter().copied().collect(),
+        };
+
+        for entry in &analysis.subroutines {
+            let sites = graph.return_sites(*entry);
+
+            for from in graph.returning_blocks(*entry) {
+                edges.extend(sites.iter().map(|to| Edge {
+                    from,
+                    to: Some(*to),
+                    kind: EdgeKind::Return,
+                }));
+            }
+        }
+
+        graph.edges = edges.into_iter().collect();
+        graph
+    }
+
+    /// Starts of the blocks following calls to the subroutine at `entry`.
+    fn return_sites(&self, entry: u16) -> Vec<u16> {
+        self.blocks
+            .values()
+            .filter_map(|block| match block.instructions.last() {
+                Some((address, Ops::CALL(target))) if *target == entry => Some(address + 2),
+                _ => None,
+            })
+            .filter(|site| self.blocks.contains_key(site))
+            .collect()
+    }
+
+    /// Starts of the blocks ending in `RET` that the subroutine at `entry` reaches without
+    /// entering the subroutines it calls.
+    fn returning_blocks(&self, entry: u16) -> Vec<u16> {
+        let mut returning = Vec::new();
+        let mut visited = BTreeSet::new();
+        let mut pending = vec![entry];
+
+        while let Some(start) = pending.pop() {
+            if !visited.insert(start) {
+                continue;
+            }
+
+            let block = match self.blocks.get(&start) {
+                Some(block) => block,
+                None => continue,
+            };
+
+            if let Some((_, Ops::RET)) = block.instructions.last() {
+                returning.push(start);
+            }
+
+            pending.extend(self.edges.iter().filter_map(|edge| match edge {
+                Edge {
+                    from,
+                    to: Some(to),
+                    kind: EdgeKind::FallThrough | EdgeKind::Skip | EdgeKind::Jump,
+                } if *from == start => Some(*to),
+                _ => None,
+            }));
+        }
+
+        returning
+    }
+}
+
+/// The instructions control can pass to after the one at `address`, not counting returns, which
+/// go wherever the subroutine was called from, and indirect jumps.
+pub fn successors(program: &[u8], origin: u16, address: u16, op: &Ops) -> Vec<(u16, EdgeKind)> {
+    let next = address.wrapping_add(length(op));
+
+    match op {
+        Ops::UNKNOWN(_) | Ops::RET | Ops::EXIT | Ops::JPV0(_) => vec![],
+        Ops::JP(target) => vec![(*target, EdgeKind::Jump)],
+        Ops::CALL(target) => vec![(*target, EdgeKind::Call), (next, EdgeKind::FallThrough)],
+        Ops::SE(_, _)
+        | Ops::SNE(_, _)
+        | Ops::SEV(_, _)
+        | Ops::SNEV(_, _)
+        | Ops::SKP(_)
+        | Ops::SKNP(_) => {
+            // XO-CHIP skips the whole of a four byte `LD I, long`
+            let skipped = match instruction_at(program, origin, next) {
+                Some(0xf000) => 4,
+                _ => INSTRUCTION_LENGTH,
+            };
+
+            vec![
+                (next, EdgeKind::FallThrough),
+                (next.wrapping_add(skipped), EdgeKind::Skip),
+            ]
+        }
+        _ => vec![(next, EdgeKind::FallThrough)],
+    }
+}
+
+/// Number of bytes taken by the operation.
+pub fn length(op: &Ops) -> u16 {
+    match op {
+        Ops::LDIL => 2 * INSTRUCTION_LENGTH,
+        _ => INSTRUCTION_LENGTH,
+    }
+}
+
+fn instruction_at(program: &[u8], origin: u16, address: u16) -> Option<u16> {
+    let offset = address.checked_sub(origin)? as usize;
+
+    program
+        .get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn depth(
+    entry: u16,
+    callees: &HashMap<u16, BTreeSet<u16>>,
+    known: &mut HashMap<u16, usize>,
+    active: &mut BTreeSet<u16>,
+) -> Option<usize> {
+    if let Some(depth) = known.get(&entry) {
+        return Some(*depth);
+    }
+
+    if !active.insert(entry) {
+        return None;
+    }
+
+    let mut deepest = 0;
+
+    for callee in callees.get(&entry).into_iter().flatten() {
+        deepest = deepest.max(depth(*callee, callees, known, active)? + 1);
+    }
+
+    active.remove(&entry);
+    known.insert(entry, deepest);
+    Some(deepest)
+}
+
+fn regions<F>(origin: u16, size: usize, included: F) -> Vec<(u16, u16)>
+where
+    F: Fn(u16) -> bool,
+{
+    let mut regions: Vec<(u16, u16)> = Vec::new();
+
+    for address in (0..size).map(|offset| origin + offset as u16) {
+        if !included(address) {
+            continue;
+        }
+
+        match regions.last_mut() {
+            Some((_, end)) if *end == address => *end += 1,
+            _ => regions.push((address, address + 1)),
+        }
+    }
+
+    regions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(instructions: &[u16]) -> Vec<u8> {
+        instructions
+            .iter()
+            .flat_map(|instruction| instruction.to_be_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn follows_jumps_and_skips_past_data() {
+        // 0x200: SE V0, 0
+        // 0x202: JP 0x208
+        // 0x204: JP 0x208, reached by the skip
+        // 0x206: data
+        // 0x208: JP 0x208
+        let analysis = Analysis::of(&program(&[0x3000, 0x1208, 0x1208, 0xffff, 0x1208]), 0x200);
+
+        assert_eq!(
+            analysis.code.keys().copied().collect::<Vec<u16>>(),
+            vec![0x200, 0x202, 0x204, 0x208]
+        );
+        assert_eq!(analysis.data_regions(), vec![(0x206, 0x208)]);
+        assert_eq!(
+            analysis.code_regions(),
+            vec![(0x200, 0x206), (0x208, 0x20a)]
+        );
+    }
+
+    #[test]
+    fn measures_subroutines_and_call_depth() {
+        // 0x200: CALL 0x206
+        // 0x202: CALL 0x20a
+        // 0x204: JP 0x204
+        // 0x206: CALL 0x20a
+        // 0x208: RET
+        // 0x20a: RET
+        let analysis = Analysis::of(
+            &program(&[0x2206, 0x220a, 0x1204, 0x220a, 0x00ee, 0x00ee]),
+            0x200,
+        );
+
+        assert_eq!(analysis.subroutines.len(), 2);
+        assert_eq!(analysis.max_call_depth, Some(2));
+    }
+
+    #[test]
+    fn recursion_has_no_maximum_call_depth() {
+        // 0x200: CALL 0x200
+        let analysis = Analysis::of(&program(&[0x2200]), 0x200);
+
+        assert_eq!(analysis.max_call_depth, None);
+    }
+
+    #[test]
+    fn reports_external_targets_sys_calls_and_indirect_jumps() {
+        // 0x200: SYS 0x123
+        // 0x202: CALL 0x800
+        // 0x204: JP V0, 0x300
+        let analysis = Analysis::of(&program(&[0x0123, 0x2800, 0xb300]), 0x200);
+
+        assert_eq!(analysis.sys_calls, vec![0x200]);
+        assert_eq!(analysis.external_targets, vec![(0x202, 0x800)]);
+        assert_eq!(analysis.indirect_jumps, vec![0x204]);
+        assert!(analysis.subroutines.is_empty());
+    }
+
+    #[test]
+    fn counts_categories_and_finds_the_variant() {
+        // 0x200: HIGH
+        // 0x202: DRW V0, V1, 5
+        // 0x204: EXIT
+        let analysis = Analysis::of(&program(&[0x00ff, 0xd015, 0x00fd]), 0x200);
+
+        assert_eq!(analysis.categories()[&Category::Display], 2);
+        assert_eq!(analysis.categories()[&Category::Flow], 1);
+        assert_eq!(analysis.variant(), Variant::Schip);
+    }
+
+    #[test]
+    fn splits_code_into_blocks_with_every_kind_of_edge() {
+        // 0x200: CALL 0x20a
+        // 0x202: SE V0, 0
+        // 0x204: JP 0x200
+        // 0x206: JP V0, 0x300
+        // 0x208: data
+        // 0x20a: LD V0, 1
+        // 0x20c: RET
+        let program = program(&[0x220a, 0x3000, 0x1200, 0xb300, 0xffff, 0x6001, 0x00ee]);
+        let graph = Graph::of(&program, &Analysis::of(&program, 0x200));
+
+        assert_eq!(
+            graph.blocks.keys().copied().collect::<Vec<u16>>(),
+            vec![0x200, 0x202, 0x204, 0x206, 0x20a]
+        );
+        assert_eq!(graph.blocks[&0x20a].end(), 0x20e);
+
+        let edge = |from, to, kind| Edge { from, to, kind };
+        assert_eq!(
+            graph.edges,
+            vec![
+                edge(0x200, Some(0x202), EdgeKind::FallThrough),
+                edge(0x200, Some(0x20a), EdgeKind::Call),
+                edge(0x202, Some(0x204), EdgeKind::FallThrough),
+                edge(0x202, Some(0x206), EdgeKind::Skip),
+                edge(0x204, Some(0x200), EdgeKind::Jump),
+                edge(0x206, None, EdgeKind::Unresolved),
+                edge(0x20a, Some(0x202), EdgeKind::Return),
+            ]
+        );
+    }
+
+    #[test]
+    fn jumps_into_a_run_split_it() {
+        // 0x200: LD V0, 1
+        // 0x202: ADD V0, 1
+        // 0x204: JP 0x202
+        let program = program(&[0x6001, 0x7001, 0x1202]);
+        let graph = Graph::of(&program, &Analysis::of(&program, 0x200));
+
+        assert_eq!(graph.blocks[&0x200].instructions.len(), 1);
+        assert_eq!(graph.blocks[&0x202].instructions.len(), 2);
+        assert!(graph.edges.contains(&Edge {
+            from: 0x202,
+            to: Some(0x202),
+            kind: EdgeKind::Jump
+        }));
+    }
+}
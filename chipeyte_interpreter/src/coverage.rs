@@ -0,0 +1,158 @@
+use crate::analysis::length;
+use crate::cpu::registers::Registers;
+use crate::operations::Ops;
+use std::{collections::BTreeSet, fmt};
+
+/// The memory a run touched, by address.
+///
+/// Written as one line per address, such as `0200 x` or `0f00 rw`, with `x` for executed, `r` for
+/// read and `w` for written. Blank lines and lines starting with `#` are ignored.
+#[derive(Debug, PartialEq, Default, Clone)]
+pub struct Coverage {
+    /// Bytes of the instructions executed.
+    pub executed: BTreeSet<u16>,
+    /// Bytes read as sprites or into registers.
+    pub read: BTreeSet<u16>,
+    /// Bytes stored to.
+    pub written: BTreeSet<u16>,
+}
+
+impl Coverage {
+    pub fn new() -> Self {
+        Coverage::default()
+    }
+
+    /// Marks the bytes of the operation at `pc` as executed, along with the bytes it reads as data
+    /// or stores, given the registers before it runs and the number of planes sprites are drawn to.
+    pub(crate) fn record(&mut self, pc: u16, op: &Ops, registers: &Registers, planes: usize) {
+        self.executed.extend(pc..pc + length(op));
+
+        let i = registers.i;
+        let bytes = |count: u16| (0..count).map(move |offset| i.wrapping_add(offset));
+
+        match op {
+            Ops::DRW(_, _, n) => self.read.extend(bytes(*n as u16 * planes as u16)),
+            Ops::LDVI(x) => self.read.extend(bytes(*x as u16 + 1)),
+            Ops::LDIV(x) => self.written.extend(bytes(*x as u16 + 1)),
+            Ops::LDB(_) => self.written.extend(bytes(3)),
+            _ => {}
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Coverage, String> {
+        let mut coverage = Coverage::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = || {
+                format!(
+                    "Invalid coverage on line {}: '{}', expected an address and flags like 0200 x",
+                    number + 1,
+                    line
+                )
+            };
+            let mut fields = line.split_whitespace();
+            let address = fields
+                .next()
+                .and_then(|address| u16::from_str_radix(address, 16).ok())
+                .ok_or_else(invalid)?;
+            let flags = fields.next().ok_or_else(invalid)?;
+
+            if fields.next().is_some() {
+                return Err(invalid());
+            }
+
+            for flag in flags.chars() {
+                match flag {
+                    'x' => coverage.executed.insert(address),
+                    'r' => coverage.read.insert(address),
+                    'w' => coverage.written.insert(address),
+                    _ => return Err(invalid()),
+                };
+            }
+        }
+
+        Ok(coverage)
+    }
+}
+
+impl fmt::Display for Coverage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let addresses: BTreeSet<&u16> = self
+            .executed
+            .iter()
+            .chain(&self.read)
+            .chain(&self.written)
+            .collect();
+
+        for address in addresses {
+            let flags: String = [
+                ('x', &self.executed),
+                ('r', &self.read),
+                ('w', &self.written),
+            ]
+            .iter()
+            .filter(|(_, addresses)| addresses.contains(address))
+            .map(|(flag, _)| *flag)
+            .collect();
+
+            writeln!(f, "{:04x} {}", address, flags)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trips_through_text() {
+        let mut coverage = Coverage::new();
+        coverage.executed.extend(&[0x200, 0x201]);
+        coverage.read.extend(&[0x201, 0x300]);
+        coverage.written.insert(0x300);
+
+        let text = coverage.to_string();
+
+        assert_eq!(text, "0200 x\n0201 xr\n0300 rw\n");
+        assert_eq!(Coverage::parse(&text).unwrap(), coverage);
+    }
+
+    #[test]
+    fn records_executed_read_and_written_bytes() {
+        let mut coverage = Coverage::new();
+        let registers = Registers {
+            i: 0x300,
+            ..Registers::new(0x200)
+        };
+
+        coverage.record(0x200, &Ops::DRW(0, 1, 2), &registers, 1);
+        coverage.record(0x202, &Ops::LDVI(1), &registers, 1);
+        coverage.record(0x204, &Ops::LDB(0), &registers, 1);
+
+        assert_eq!(
+            coverage.to_string().lines().collect::<Vec<&str>>(),
+            vec![
+                "0200 x", "0201 x", "0202 x", "0203 x", "0204 x", "0205 x", "0300 rw", "0301 rw",
+                "0302 w",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_comments_and_rejects_bad_lines() {
+        let coverage = Coverage::parse("# coverage\n\n0202 x\n").unwrap();
+
+        assert!(coverage.executed.contains(&0x202));
+        assert!(Coverage::parse("0202").is_err());
+        assert!(Coverage::parse("0202 q").is_err());
+        assert!(Coverage::parse("zz x").is_err());
+    }
+}
@@ -1,34 +1,144 @@
+pub mod block_cache;
 pub mod instruction_decoder;
 pub mod registers;
 
+use crate::coverage::Coverage;
+use crate::cpu::block_cache::{written_range, BlockCache};
 use crate::cpu::instruction_decoder::decode;
 use crate::cpu::registers::Registers;
+use crate::framebuffer::FrameBuffer;
 use crate::interface;
 use crate::memory::Memory;
+use crate::profile::Profile;
+use crate::quirks::Quirks;
+use crate::rng::Rng;
+use crate::timing::Timing;
+use crate::trace::{changed_registers, stored_bytes, TraceEntry, Tracer};
+use crate::Engine;
 use crate::{errors::ChipeyteError, operations::Ops};
 use std::fmt::Display;
 
 pub const PROGRAM_START: u16 = 0x0200;
 pub const INSTRUCTION_LENGTH: u16 = 2;
 
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq)]
 pub struct CPU {
     pub counter: u32,
+    /// Machine cycles taken by the instructions executed, counted under `Timing::CosmacVip`.
+    pub cycles: u64,
+    /// Machine cycles taken by the last executed instruction.
+    pub last_cycles: u32,
     pub registers: Registers,
+    engine: Engine,
+    blocks: BlockCache,
+    rng: Rng,
+    quirks: Quirks,
+    timing: Timing,
+    tracer: Option<Tracer>,
+    profile: Option<Profile>,
+    coverage: Option<Coverage>,
+}
+
+/// The result of a single dispatch.
+#[derive(Debug, PartialEq)]
+pub struct Step {
+    /// Program counter after the last executed operation.
+    pub pc: u16,
+    /// The last executed operation.
+    pub op: Ops,
+    /// Number of instructions executed by the dispatch.
+    pub executed: u32,
 }
 
 impl CPU {
-    pub fn new(initial_pc: u16) -> CPU {
+    pub fn new(initial_pc: u16, engine: Engine, rng: Rng, quirks: Quirks, timing: Timing) -> CPU {
         CPU {
             counter: 0,
+            cycles: 0,
+            last_cycles: 0,
             registers: Registers::new(initial_pc),
+            engine,
+            blocks: BlockCache::new(),
+            rng,
+            quirks,
+            timing,
+            tracer: None,
+            profile: None,
+            coverage: None,
+        }
+    }
+
+    /// Starts recording a trace of the instructions executed.
+    pub(crate) fn trace(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    /// Starts counting the instructions executed.
+    pub(crate) fn start_profiling(&mut self, profile: Profile) {
+        self.profile = Some(profile);
+    }
+
+    pub(crate) fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    pub(crate) fn profile_mut(&mut self) -> Option<&mut Profile> {
+        self.profile.as_mut()
+    }
+
+    pub(crate) fn take_profile(&mut self) -> Option<Profile> {
+        self.profile.take()
+    }
+
+    /// Starts recording the memory executed, read and written.
+    pub(crate) fn start_coverage(&mut self, coverage: Coverage) {
+        self.coverage = Some(coverage);
+    }
+
+    pub(crate) fn coverage(&self) -> Option<&Coverage> {
+        self.coverage.as_ref()
+    }
+
+    pub(crate) fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    /// Takes the trace entries recorded since the last call.
+    pub(crate) fn take_trace(&mut self) -> Vec<TraceEntry> {
+        self.tracer
+            .as_mut()
+            .map(|tracer| tracer.drain())
+            .unwrap_or_default()
+    }
+
+    /// Executes the next instruction, or the next translated block when running the
+    /// `Engine::BasicBlock` backend.
+    pub fn step(
+        &mut self,
+        memory: &mut Memory,
+        frame_buffer: &mut FrameBuffer,
+        controller: &mut dyn interface::Controllable,
+    ) -> Result<Step, ChipeyteError> {
+        match self.engine {
+            Engine::Interpreter => {
+                let counter = self.counter;
+                let (pc, op) = self.tick(memory, frame_buffer, controller)?;
+
+                Ok(Step {
+                    pc,
+                    op,
+                    executed: self.counter - counter,
+                })
+            }
+            Engine::BasicBlock => self.run_block(memory, frame_buffer, controller),
         }
     }
 
     pub fn tick(
         &mut self,
         memory: &mut Memory,
-        screen: &mut dyn interface::Drawable,
+        frame_buffer: &mut FrameBuffer,
         controller: &mut dyn interface::Controllable,
     ) -> Result<(u16, Ops), ChipeyteError> {
         let instruction = self.fetch(memory);
@@ -39,12 +149,112 @@ impl CPU {
             return Ok((self.registers.pc, Ops::UNKNOWN(instruction)));
         }
 
-        self.registers.pc += INSTRUCTION_LENGTH;
-        self.execute(operation, memory, screen, controller)?;
+        self.run_op(operation, memory, frame_buffer, controller)?;
 
         Ok((self.registers.pc, operation))
     }
 
+    fn run_block(
+        &mut self,
+        memory: &mut Memory,
+        frame_buffer: &mut FrameBuffer,
+        controller: &mut dyn interface::Controllable,
+    ) -> Result<Step, ChipeyteError> {
+        let block = self.blocks.fetch(self.registers.pc, memory);
+
+        if block.ops.is_empty() {
+            return Ok(Step {
+                pc: self.registers.pc,
+                op: Ops::UNKNOWN(0),
+                executed: 0,
+            });
+        }
+
+        for op in block.ops.iter() {
+            let write = written_range(op, &self.registers);
+
+            self.run_op(*op, memory, frame_buffer, controller)?;
+
+            if let Some((from, to)) = write {
+                self.blocks.invalidate(from, to);
+            }
+        }
+
+        Ok(Step {
+            pc: self.registers.pc,
+            op: *block.ops.last().unwrap(),
+            executed: block.ops.len() as u32,
+        })
+    }
+
+    /// Executes the operation at the program counter, tracing it when recording.
+    fn run_op(
+        &mut self,
+        op: Ops,
+        memory: &mut Memory,
+        frame_buffer: &mut FrameBuffer,
+        controller: &mut dyn interface::Controllable,
+    ) -> Result<(), ChipeyteError> {
+        let pc = self.registers.pc;
+        let cycle = self.counter as u64;
+        let recording = match &mut self.tracer {
+            Some(tracer) => tracer.is_recording(cycle, pc),
+            None => false,
+        };
+        let traced = if recording {
+            Some((
+                self.fetch(memory),
+                self.registers.clone(),
+                written_range(&op, &self.registers),
+            ))
+        } else {
+            None
+        };
+
+        let timed = match self.timing {
+            Timing::Fixed => None,
+            Timing::CosmacVip => Some(self.registers.clone()),
+        };
+
+        if let Some(coverage) = &mut self.coverage {
+            coverage.record(
+                pc,
+                &op,
+                &self.registers,
+                frame_buffer.selected_plane_count(),
+            );
+        }
+
+        self.registers.pc += INSTRUCTION_LENGTH;
+        self.execute(op, memory, frame_buffer, controller)?;
+        self.counter += 1;
+
+        if let Some(before) = timed {
+            self.last_cycles = self.timing.cycles(&op, &before, &self.registers);
+            self.cycles += self.last_cycles as u64;
+        }
+
+        #[cfg(feature = "logging")]
+        log::debug!("{:04x?}: {:?}", self.registers.pc, op);
+
+        if let Some(profile) = &mut self.profile {
+            profile.record(pc, &op);
+        }
+
+        if let (Some(tracer), Some((opcode, before, written))) = (&mut self.tracer, traced) {
+            tracer.record(TraceEntry {
+                cycle,
+                pc,
+                opcode,
+                op,
+                registers: changed_registers(&before, &self.registers),
+                writes: stored_bytes(written, memory),
+            });
+        }
+
+        Ok(())
+    }
+
     fn fetch(&self, memory: &Memory) -> u16 {
         memory.get_u16(self.registers.pc.into())
     }
@@ -53,10 +263,17 @@ impl CPU {
         &mut self,
         operation: Ops,
         memory: &mut Memory,
-        screen: &mut dyn interface::Drawable,
+        frame_buffer: &mut FrameBuffer,
         controller: &mut dyn interface::Controllable,
     ) -> Result<(), ChipeyteError> {
-        operation.call(&mut self.registers, memory, screen, controller)
+        operation.call(
+            &mut self.registers,
+            memory,
+            frame_buffer,
+            controller,
+            &mut self.rng,
+            &self.quirks,
+        )
     }
 }
 
@@ -0,0 +1,274 @@
+use crate::cpu::instruction_decoder::decode;
+use crate::cpu::registers::Registers;
+use crate::cpu::INSTRUCTION_LENGTH;
+use crate::memory::Memory;
+use crate::operations::Ops;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Upper limit on the number of instructions translated into a single block.
+const MAX_BLOCK_LENGTH: usize = 64;
+
+/// A straight-line run of pre-decoded operations starting at `start`.
+///
+/// A block ends after the first operation that can change the flow of control (jumps, calls,
+/// returns, skips and key waits) or that writes to memory, so the remainder of a block can never
+/// contain stale code. Blocks also end after `DRW` so the frame loop can stall on the display wait.
+#[derive(Debug, PartialEq)]
+pub struct Block {
+    pub start: u16,
+    pub ops: Vec<Ops>,
+}
+
+impl Block {
+    /// Address of the first byte after the block.
+    pub fn end(&self) -> u16 {
+        self.start + self.ops.len() as u16 * INSTRUCTION_LENGTH
+    }
+
+    fn overlaps(&self, from: u16, to: u16) -> bool {
+        self.start <= to && from < self.end()
+    }
+}
+
+/// Translated blocks keyed by their start address.
+#[derive(Debug, PartialEq, Default)]
+pub struct BlockCache {
+    blocks: HashMap<u16, Rc<Block>>,
+}
+
+impl BlockCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the block starting at `pc`, translating it from memory on a cache miss.
+    pub fn fetch(&mut self, pc: u16, memory: &Memory) -> Rc<Block> {
+        self.blocks
+            .entry(pc)
+            .or_insert_with(|| Rc::new(translate(pc, memory)))
+            .clone()
+    }
+
+    /// Drops every block containing an address in the range `from..=to`.
+    pub fn invalidate(&mut self, from: u16, to: u16) {
+        self.blocks.retain(|_, block| !block.overlaps(from, to));
+    }
+}
+
+/// Returns the memory range `op` is about to write to, given the register state before executing
+/// it.
+pub fn written_range(op: &Ops, registers: &Registers) -> Option<(u16, u16)> {
+    match op {
+        Ops::CALL(_) => {
+            let entry = registers.sp as u16 + 2;
+            Some((entry, entry + 1))
+        }
+        Ops::LDB(_) => Some((registers.i, registers.i + 2)),
+        Ops::LDIV(vx) => Some((registers.i, registers.i + *vx as u16)),
+        _ => None,
+    }
+}
+
+fn ends_block(op: &Ops) -> bool {
+    matches!(
+        op,
+        Ops::UNKNOWN(_)
+            | Ops::RET
+            | Ops::JP(_)
+            | Ops::CALL(_)
+            | Ops::SE(_, _)
+            | Ops::SNE(_, _)
+            | Ops::SEV(_, _)
+            | Ops::SNEV(_, _)
+            | Ops::JPV0(_)
+            | Ops::SKP(_)
+            | Ops::SKNP(_)
+            | Ops::LDK(_)
+            | Ops::DRW(_, _, _)
+            | Ops::LDB(_)
+            | Ops::LDIV(_)
+            | Ops::EXIT
+            | Ops::SAVE(_, _)
+            | Ops::LDIL
+    )
+}
+
+fn translate(start: u16, memory: &Memory) -> Block {
+    let mut ops = Vec::new();
+    let mut addr = start as usize;
+
+    // Instructions are two bytes, so the last complete one starts at 0x0ffe.
+    while addr < 0x0fff && ops.len() < MAX_BLOCK_LENGTH {
+        let instruction = memory.get_u16(addr);
+
+        // The interpreter halts on a zero instruction, so it must never be part of a block.
+        if instruction == 0 {
+            break;
+        }
+
+        let op = decode(instruction);
+        ops.push(op);
+
+        if ends_block(&op) {
+            break;
+        }
+
+        addr += INSTRUCTION_LENGTH as usize;
+    }
+
+    Block { start, ops }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cpu::{CPU, PROGRAM_START};
+    use crate::framebuffer::FrameBuffer;
+    use crate::interface::Controller;
+    use crate::quirks::Quirks;
+    use crate::rng::Rng;
+    use crate::timing::Timing;
+    use crate::Engine;
+    use std::fs;
+    use std::path::Path;
+
+    struct Machine {
+        cpu: CPU,
+        memory: Memory,
+        frame_buffer: FrameBuffer,
+        controller: Controller,
+    }
+
+    impl Machine {
+        fn new(engine: Engine, program: &[u8]) -> Self {
+            let mut memory = Memory::new();
+            memory.load_program(PROGRAM_START.into(), program);
+
+            Self {
+                cpu: CPU::new(
+                    PROGRAM_START,
+                    engine,
+                    Rng::new(1234),
+                    Quirks::default(),
+                    Timing::Fixed,
+                ),
+                memory,
+                frame_buffer: FrameBuffer::new(),
+                controller: Controller::new(),
+            }
+        }
+
+        fn step(&mut self) -> Ops {
+            self.cpu
+                .step(
+                    &mut self.memory,
+                    &mut self.frame_buffer,
+                    &mut self.controller,
+                )
+                .unwrap()
+                .op
+        }
+    }
+
+    fn program(bytes: &[u16]) -> Vec<u8> {
+        bytes
+            .iter()
+            .flat_map(|x| x.to_be_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn block_ends_after_control_flow() {
+        let mut memory = Memory::new();
+        memory.load_program(
+            PROGRAM_START.into(),
+            &program(&[0x6a10, 0x7a01, 0x3a20, 0x1200, 0x6b01]),
+        );
+
+        let block = BlockCache::new().fetch(PROGRAM_START, &memory);
+
+        assert_eq!(
+            block.ops,
+            vec![Ops::LD(0xa, 0x10), Ops::ADD(0xa, 0x01), Ops::SE(0xa, 0x20)]
+        );
+        assert_eq!(block.end(), PROGRAM_START + 6);
+    }
+
+    #[test]
+    fn block_stops_before_zero_instruction() {
+        let mut memory = Memory::new();
+        memory.load_program(PROGRAM_START.into(), &program(&[0x6a10, 0x0000, 0x6b01]));
+
+        let block = BlockCache::new().fetch(PROGRAM_START, &memory);
+
+        assert_eq!(block.ops, vec![Ops::LD(0xa, 0x10)]);
+    }
+
+    #[test]
+    fn invalidate_drops_overlapping_blocks() {
+        let mut memory = Memory::new();
+        memory.load_program(
+            PROGRAM_START.into(),
+            &program(&[0x6a10, 0x1200, 0x6b01, 0x1204]),
+        );
+
+        let mut cache = BlockCache::new();
+        cache.fetch(PROGRAM_START, &memory);
+        cache.fetch(PROGRAM_START + 4, &memory);
+        assert_eq!(cache.blocks.len(), 2);
+
+        cache.invalidate(PROGRAM_START + 5, PROGRAM_START + 5);
+
+        assert_eq!(cache.blocks.len(), 1);
+    }
+
+    #[test]
+    fn self_modifying_write_is_picked_up() {
+        // 0x200: LD I, 0x20a
+        // 0x202: LD V0, 0x6b
+        // 0x204: LD V1, 0x07
+        // 0x206: LD [I], V1    overwrites 0x20a with LD VB, 0x07
+        // 0x208: JP 0x20a
+        // 0x20a: LD VA, 0x01
+        let bytes = program(&[0xa20a, 0x606b, 0x6107, 0xf155, 0x120a, 0x6a01, 0x0000]);
+        let mut machine = Machine::new(Engine::BasicBlock, &bytes);
+
+        // Warm the cache with the original instruction at 0x20a
+        machine.cpu.blocks.fetch(0x020a, &machine.memory);
+
+        while machine.step() != Ops::UNKNOWN(0) {}
+
+        assert_eq!(machine.cpu.registers.vb, 0x07);
+        assert_eq!(machine.cpu.registers.va, 0x00);
+    }
+
+    #[test]
+    fn basic_block_engine_matches_interpreter_on_bundled_programs() {
+        let programs_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../programs");
+
+        for entry in fs::read_dir(programs_dir).unwrap() {
+            let path = entry.unwrap().path();
+            let program = fs::read(&path).unwrap();
+
+            let mut reference = Machine::new(Engine::Interpreter, &program);
+            let mut blocks = Machine::new(Engine::BasicBlock, &program);
+
+            while blocks.cpu.counter < 20_000 {
+                let op = blocks.step();
+
+                while reference.cpu.counter < blocks.cpu.counter {
+                    reference.step();
+                }
+
+                assert_eq!(reference.cpu.registers, blocks.cpu.registers, "{:?}", path);
+                assert_eq!(reference.memory, blocks.memory, "{:?}", path);
+                assert_eq!(reference.frame_buffer, blocks.frame_buffer, "{:?}", path);
+
+                if op == Ops::UNKNOWN(0) {
+                    break;
+                }
+            }
+        }
+    }
+}
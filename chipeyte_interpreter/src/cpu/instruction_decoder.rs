@@ -5,12 +5,21 @@ pub fn decode(instruction: u16) -> Ops {
     match to_nibbles(instruction) {
         (0x0, 0x0, 0xE, 0x0) => Ops::CLS,
         (0x0, 0x0, 0xE, 0xE) => Ops::RET,
+        (0x0, 0x0, 0xC, n) => Ops::SCD(n),
+        (0x0, 0x0, 0xD, n) => Ops::SCU(n),
+        (0x0, 0x0, 0xF, 0xB) => Ops::SCR,
+        (0x0, 0x0, 0xF, 0xC) => Ops::SCL,
+        (0x0, 0x0, 0xF, 0xD) => Ops::EXIT,
+        (0x0, 0x0, 0xF, 0xE) => Ops::LOW,
+        (0x0, 0x0, 0xF, 0xF) => Ops::HIGH,
         (0x0, x, y, z) => Ops::SYS(to_addr(x, y, z)),
         (0x1, x, y, z) => Ops::JP(to_addr(x, y, z)),
         (0x2, x, y, z) => Ops::CALL(to_addr(x, y, z)),
         (0x3, vx, hi, lo) => Ops::SE(vx, nibbles_to_byte(hi, lo)),
         (0x4, vx, hi, lo) => Ops::SNE(vx, nibbles_to_byte(hi, lo)),
         (0x5, vx, vy, 0x0) => Ops::SEV(vx, vy),
+        (0x5, vx, vy, 0x2) => Ops::SAVE(vx, vy),
+        (0x5, vx, vy, 0x3) => Ops::LOAD(vx, vy),
         (0x6, vx, hi, lo) => Ops::LD(vx, nibbles_to_byte(hi, lo)),
         (0x7, vx, hi, lo) => Ops::ADD(vx, nibbles_to_byte(hi, lo)),
         (0x8, vx, vy, 0x0) => Ops::LDV(vx, vy),
@@ -19,9 +28,9 @@ pub fn decode(instruction: u16) -> Ops {
         (0x8, vx, vy, 0x3) => Ops::XOR(vx, vy),
         (0x8, vx, vy, 0x4) => Ops::ADDV(vx, vy),
         (0x8, vx, vy, 0x5) => Ops::SUB(vx, vy),
-        (0x8, vx, _, 0x6) => Ops::SHR(vx),
+        (0x8, vx, vy, 0x6) => Ops::SHR(vx, vy),
         (0x8, vx, vy, 0x7) => Ops::SUBN(vx, vy),
-        (0x8, vx, _, 0xE) => Ops::SHL(vx),
+        (0x8, vx, vy, 0xE) => Ops::SHL(vx, vy),
         (0x9, vx, vy, 0x0) => Ops::SNEV(vx, vy),
         (0xA, x, y, z) => Ops::LDI(to_addr(x, y, z)),
         (0xB, x, y, z) => Ops::JPV0(to_addr(x, y, z)),
@@ -29,15 +38,22 @@ pub fn decode(instruction: u16) -> Ops {
         (0xD, vx, vy, n) => Ops::DRW(vx, vy, n),
         (0xE, vx, 0x9, 0xE) => Ops::SKP(vx),
         (0xE, vx, 0xA, 0x1) => Ops::SKNP(vx),
+        (0xF, 0x0, 0x0, 0x0) => Ops::LDIL,
+        (0xF, 0x0, 0x0, 0x2) => Ops::AUDIO,
+        (0xF, n, 0x0, 0x1) => Ops::PLANE(n),
         (0xF, vx, 0x0, 0x7) => Ops::LDVDT(vx),
         (0xF, vx, 0x0, 0xA) => Ops::LDK(vx),
         (0xF, vx, 0x1, 0x5) => Ops::LDDT(vx),
         (0xF, vx, 0x1, 0x8) => Ops::LDST(vx),
         (0xF, vx, 0x1, 0xE) => Ops::ADDI(vx),
         (0xF, vx, 0x2, 0x9) => Ops::LDF(vx),
+        (0xF, vx, 0x3, 0x0) => Ops::LDHF(vx),
+        (0xF, vx, 0x3, 0xA) => Ops::PITCH(vx),
         (0xF, vx, 0x3, 0x3) => Ops::LDB(vx),
         (0xF, vx, 0x5, 0x5) => Ops::LDIV(vx),
         (0xF, vx, 0x6, 0x5) => Ops::LDVI(vx),
+        (0xF, vx, 0x7, 0x5) => Ops::LDRV(vx),
+        (0xF, vx, 0x8, 0x5) => Ops::LDVR(vx),
         _ => Ops::UNKNOWN(instruction),
     }
 }
@@ -78,4 +94,18 @@ mod tests {
         assert_eq!(decode(0x7D01), Ops::ADD(0xD, 0x01));
         assert_eq!(decode(0xEEEE), Ops::UNKNOWN(0xEEEE));
     }
+
+    #[test]
+    fn decode_recognises_super_chip_and_xo_chip_ops() {
+        assert_eq!(decode(0x00C4), Ops::SCD(0x4));
+        assert_eq!(decode(0x00D2), Ops::SCU(0x2));
+        assert_eq!(decode(0x00FF), Ops::HIGH);
+        assert_eq!(decode(0x00FD), Ops::EXIT);
+        assert_eq!(decode(0x5122), Ops::SAVE(0x1, 0x2));
+        assert_eq!(decode(0xF000), Ops::LDIL);
+        assert_eq!(decode(0xF201), Ops::PLANE(0x2));
+        assert_eq!(decode(0xF002), Ops::AUDIO);
+        assert_eq!(decode(0xF330), Ops::LDHF(0x3));
+        assert_eq!(decode(0xF485), Ops::LDVR(0x4));
+    }
 }
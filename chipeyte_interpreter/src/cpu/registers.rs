@@ -1,6 +1,6 @@
 use crate::errors::ChipeyteError;
 
-#[derive(Debug, PartialEq, Eq, Default)]
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
 pub struct Registers {
     pub i: u16,  // Stores memory addresses, only lowest 12 bits used.
     pub pc: u16, // program counter
@@ -0,0 +1,57 @@
+use crate::cpu::INSTRUCTION_LENGTH;
+use std::fmt;
+
+pub use crate::cpu::instruction_decoder::decode;
+pub use crate::operations::{Category, Ops};
+
+/// A decoded instruction and the address it was read from.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Instruction {
+    pub address: u16,
+    pub opcode: u16,
+    pub op: Ops,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:04x}  {:04x}  {}", self.address, self.opcode, self.op)
+    }
+}
+
+/// Decodes every two bytes of `program`, as loaded at `origin`, as an instruction.
+///
+/// The disassembly is linear, so data embedded in the program shows up as instructions as well. A
+/// trailing odd byte is left out.
+pub fn disassemble(program: &[u8], origin: u16) -> Vec<Instruction> {
+    program
+        .chunks_exact(INSTRUCTION_LENGTH as usize)
+        .enumerate()
+        .map(|(index, bytes)| {
+            let opcode = u16::from_be_bytes([bytes[0], bytes[1]]);
+
+            Instruction {
+                address: origin.wrapping_add(index as u16 * INSTRUCTION_LENGTH),
+                opcode,
+                op: decode(opcode),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn disassemble_lists_instructions_with_their_addresses() {
+        let listing = disassemble(&[0x6a, 0x05, 0xf1, 0x65, 0x12], 0x200)
+            .iter()
+            .map(|instruction| instruction.to_string())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            listing,
+            vec!["0200  6a05  LD VA, 0x05", "0202  f165  LD V1, [I]"]
+        );
+    }
+}
@@ -9,11 +9,19 @@ pub enum ChipeyteError {
     OpFailed(Ops, String),
     UnsupportedSprite(u8),
     UnknownKey(u8),
+    BadColor(String),
+    BadWaveform(String),
+    BadVariant(String),
+    BadRegister(String),
+    BadTrigger(String),
+    BadTraceFormat(String),
+    BadTiming(String),
+    TraceFailed(String),
 }
 
 impl fmt::Display for ChipeyteError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &*self {
+        match self {
             ChipeyteError::OpNotImplemented(op) => {
                 write!(f, "Operation {:?} not yet implemented!", op)
             }
@@ -29,14 +37,54 @@ impl fmt::Display for ChipeyteError {
             ChipeyteError::UnsupportedSprite(digit) => write!(f, "Unsupported sprite {:x?}", digit),
 
             ChipeyteError::UnknownKey(key) => write!(f, "Unknown key: {:x?}", key),
+
+            ChipeyteError::BadColor(color) => {
+                write!(
+                    f,
+                    "Invalid colour '{}', expected a hex string like #00ff00",
+                    color
+                )
+            }
+
+            ChipeyteError::BadWaveform(waveform) => write!(
+                f,
+                "Unknown waveform '{}', expected square, triangle, sine, sawtooth or noise",
+                waveform
+            ),
+
+            ChipeyteError::BadVariant(variant) => write!(
+                f,
+                "Unknown variant '{}', expected chip8, schip or xochip",
+                variant
+            ),
+
+            ChipeyteError::BadRegister(register) => write!(f, "Unknown register '{}'", register),
+
+            ChipeyteError::BadTrigger(trigger) => write!(
+                f,
+                "Invalid trigger '{}', expected pc=ADDR, pc=ADDR-ADDR or cycle=N",
+                trigger
+            ),
+
+            ChipeyteError::BadTraceFormat(format) => write!(
+                f,
+                "Unknown trace format '{}', expected text or jsonl",
+                format
+            ),
+
+            ChipeyteError::BadTiming(timing) => {
+                write!(f, "Unknown timing '{}', expected fixed or vip", timing)
+            }
+
+            ChipeyteError::TraceFailed(message) => {
+                write!(f, "Cannot write the trace: {}", message)
+            }
         }
     }
 }
 
 impl error::Error for ChipeyteError {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
-        match *self {
-            _ => None,
-        }
+        None
     }
 }
@@ -0,0 +1,338 @@
+/// Number of bit planes. Chip-8 and Super-Chip use a single plane, XO-CHIP draws on two.
+pub const PLANES: usize = 2;
+
+const MAX_HEIGHT: usize = 64;
+
+/// Display resolutions used by the Chip-8 family.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Resolution {
+    /// 64 x 32, the original Chip-8 display.
+    Low,
+    /// 64 x 64, used by the two-page "hires" Chip-8 variant.
+    Tall,
+    /// 128 x 64, Super-Chip and XO-CHIP high resolution mode.
+    High,
+}
+
+impl Resolution {
+    pub fn width(&self) -> usize {
+        match self {
+            Resolution::Low | Resolution::Tall => 64,
+            Resolution::High => 128,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match self {
+            Resolution::Low => 32,
+            Resolution::Tall | Resolution::High => 64,
+        }
+    }
+}
+
+/// The Chip-8 display memory.
+///
+/// Each row of each plane is stored as a bitmask where the most significant bit is the leftmost
+/// pixel, so a sprite row is drawn with a single XOR and collisions are detected with a single
+/// AND.
+///
+/// Rows changed since the frame buffer was last presented are tracked in a bitmask, so front ends
+/// only need to present when something actually changed.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FrameBuffer {
+    resolution: Resolution,
+    planes: [[u128; MAX_HEIGHT]; PLANES],
+    selected_planes: u8,
+    dirty_rows: u64,
+}
+
+impl FrameBuffer {
+    pub fn new() -> Self {
+        Self {
+            resolution: Resolution::Low,
+            planes: [[0; MAX_HEIGHT]; PLANES],
+            selected_planes: 0b01,
+            dirty_rows: !0,
+        }
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.resolution
+    }
+
+    pub fn width(&self) -> usize {
+        self.resolution.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.resolution.height()
+    }
+
+    /// Switches resolution, which clears every plane.
+    pub fn set_resolution(&mut self, resolution: Resolution) {
+        self.resolution = resolution;
+        self.planes = [[0; MAX_HEIGHT]; PLANES];
+        self.dirty_rows = !0;
+    }
+
+    /// Selects the planes affected by drawing and clearing, bit 0 being the first plane.
+    pub fn select_planes(&mut self, mask: u8) {
+        self.selected_planes = mask & ((1 << PLANES) - 1);
+    }
+
+    pub fn selected_planes(&self) -> u8 {
+        self.selected_planes
+    }
+
+    /// Number of planes a sprite is drawn to, each consuming its own rows of sprite data.
+    pub fn selected_plane_count(&self) -> usize {
+        self.selected_planes.count_ones() as usize
+    }
+
+    /// Clears the selected planes.
+    pub fn clear(&mut self) {
+        for plane in self.selected().collect::<Vec<usize>>() {
+            for (y, row) in self.planes[plane].iter_mut().enumerate() {
+                if *row != 0 {
+                    *row = 0;
+                    self.dirty_rows |= 1 << y;
+                }
+            }
+        }
+    }
+
+    /// XORs a sprite onto the selected planes at (`x`, `y`), wrapping around the edges of the
+    /// display, and returns whether any lit pixel was erased.
+    ///
+    /// When several planes are selected, `sprite` holds the rows for each plane one after the
+    /// other.
+    pub fn draw_sprite(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        self.blit(x, y, sprite, true)
+    }
+
+    /// Like `draw_sprite`, but pixels that fall off the right or bottom edge are dropped instead of
+    /// wrapping around. The sprite's position still wraps.
+    pub fn draw_sprite_clipped(&mut self, x: u8, y: u8, sprite: &[u8]) -> bool {
+        self.blit(x, y, sprite, false)
+    }
+
+    fn blit(&mut self, x: u8, y: u8, sprite: &[u8], wrap: bool) -> bool {
+        let width = self.width();
+        let height = self.height();
+        let x = x as usize % width;
+        let y = y as usize % height;
+        let planes = self.selected().collect::<Vec<usize>>();
+
+        if planes.is_empty() {
+            return false;
+        }
+
+        let rows_per_plane = sprite.len() / planes.len();
+        let mut collision = false;
+
+        for (index, plane) in planes.into_iter().enumerate() {
+            let rows = &sprite[index * rows_per_plane..(index + 1) * rows_per_plane];
+
+            for (offset, byte) in rows.iter().enumerate() {
+                if !wrap && y + offset >= height {
+                    break;
+                }
+
+                let bits = sprite_row(*byte, x, width, wrap);
+                let row_index = (y + offset) % height;
+                let row = &mut self.planes[plane][row_index];
+
+                collision |= *row & bits != 0;
+                *row ^= bits;
+
+                if bits != 0 {
+                    self.dirty_rows |= 1 << row_index;
+                }
+            }
+        }
+
+        collision
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty_rows != 0
+    }
+
+    /// Returns the rows changed since the last call to `mark_clean`, bit `y` being row `y`.
+    pub fn dirty_rows(&self) -> u64 {
+        self.dirty_rows
+    }
+
+    /// Marks the frame buffer as presented.
+    pub fn mark_clean(&mut self) {
+        self.dirty_rows = 0;
+    }
+
+    /// Returns the planes lit at (`x`, `y`) as a bitmask, bit 0 being the first plane.
+    pub fn pixel(&self, x: usize, y: usize) -> u8 {
+        let bit = 1 << (127 - x);
+
+        (0..PLANES)
+            .filter(|plane| self.planes[*plane][y] & bit != 0)
+            .fold(0, |mask, plane| mask | 1 << plane)
+    }
+
+    pub fn is_lit(&self, x: usize, y: usize) -> bool {
+        self.pixel(x, y) != 0
+    }
+
+    /// Returns the bitmask of row `y` of `plane`, leftmost pixel in the most significant bit.
+    pub fn row(&self, plane: usize, y: usize) -> u128 {
+        self.planes[plane][y]
+    }
+
+    /// Iterates over the coordinates of every lit pixel, row by row.
+    pub fn lit_pixels(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.height()).flat_map(move |y| {
+            let row = (0..PLANES).fold(0, |row, plane| row | self.planes[plane][y]);
+
+            (0..self.width())
+                .filter(move |x| row & (1 << (127 - x)) != 0)
+                .map(move |x| (x, y))
+        })
+    }
+
+    fn selected(&self) -> impl Iterator<Item = usize> {
+        let mask = self.selected_planes;
+
+        (0..PLANES).filter(move |plane| mask & (1 << plane) != 0)
+    }
+}
+
+impl Default for FrameBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Places a sprite byte at column `x` of a row `width` pixels wide, wrapping pixels that fall off
+/// the right edge around to the left if `wrap` is set.
+fn sprite_row(byte: u8, x: usize, width: usize, wrap: bool) -> u128 {
+    let aligned = (byte as u128) << 120;
+    let mut bits = aligned >> x;
+
+    if wrap && x + 8 > width {
+        bits |= aligned << (width - x);
+    }
+
+    bits & (!0u128 << (128 - width))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_sprite_lights_pixels() {
+        let mut fb = FrameBuffer::new();
+
+        let collision = fb.draw_sprite(2, 3, &[0b1010_0000]);
+
+        assert!(!collision);
+        assert_eq!(fb.lit_pixels().collect::<Vec<_>>(), vec![(2, 3), (4, 3)]);
+    }
+
+    #[test]
+    fn draw_sprite_twice_erases_it_and_reports_collision() {
+        let mut fb = FrameBuffer::new();
+
+        fb.draw_sprite(10, 10, &[0xff, 0x81]);
+        let collision = fb.draw_sprite(10, 10, &[0xff, 0x81]);
+
+        assert!(collision);
+        assert_eq!(fb.lit_pixels().count(), 0);
+    }
+
+    #[test]
+    fn draw_sprite_wraps_around_the_edges() {
+        let mut fb = FrameBuffer::new();
+
+        fb.draw_sprite(62, 31, &[0b1110_0000, 0b1000_0000]);
+
+        assert_eq!(
+            fb.lit_pixels().collect::<Vec<_>>(),
+            vec![(62, 0), (0, 31), (62, 31), (63, 31)]
+        );
+    }
+
+    #[test]
+    fn draw_sprite_clipped_drops_pixels_past_the_edges() {
+        let mut fb = FrameBuffer::new();
+
+        fb.draw_sprite_clipped(62, 31, &[0b1110_0000, 0b1000_0000]);
+
+        assert_eq!(
+            fb.lit_pixels().collect::<Vec<_>>(),
+            vec![(62, 31), (63, 31)]
+        );
+    }
+
+    #[test]
+    fn draw_sprite_wraps_in_high_resolution() {
+        let mut fb = FrameBuffer::new();
+        fb.set_resolution(Resolution::High);
+
+        fb.draw_sprite(127, 63, &[0b1100_0000]);
+
+        assert_eq!(
+            fb.lit_pixels().collect::<Vec<_>>(),
+            vec![(0, 63), (127, 63)]
+        );
+    }
+
+    #[test]
+    fn clear_only_clears_selected_planes() {
+        let mut fb = FrameBuffer::new();
+        fb.select_planes(0b11);
+        fb.draw_sprite(0, 0, &[0b1000_0000, 0b1100_0000]);
+
+        assert_eq!(fb.pixel(0, 0), 0b11);
+        assert_eq!(fb.pixel(1, 0), 0b10);
+
+        fb.select_planes(0b10);
+        fb.clear();
+
+        assert_eq!(fb.pixel(0, 0), 0b01);
+        assert_eq!(fb.pixel(1, 0), 0b00);
+    }
+
+    #[test]
+    fn draw_sprite_marks_touched_rows_dirty() {
+        let mut fb = FrameBuffer::new();
+        fb.mark_clean();
+
+        fb.draw_sprite(0, 31, &[0xff, 0x00, 0x01]);
+
+        assert_eq!(fb.dirty_rows(), 1 << 31 | 1 << 1);
+    }
+
+    #[test]
+    fn clear_only_marks_lit_rows_dirty() {
+        let mut fb = FrameBuffer::new();
+        fb.draw_sprite(0, 4, &[0xff]);
+        fb.mark_clean();
+
+        fb.clear();
+        assert_eq!(fb.dirty_rows(), 1 << 4);
+
+        fb.mark_clean();
+        fb.clear();
+        assert!(!fb.is_dirty());
+    }
+
+    #[test]
+    fn set_resolution_clears_the_display() {
+        let mut fb = FrameBuffer::new();
+        fb.draw_sprite(0, 0, &[0xff]);
+
+        fb.set_resolution(Resolution::Tall);
+
+        assert_eq!((fb.width(), fb.height()), (64, 64));
+        assert_eq!(fb.lit_pixels().count(), 0);
+    }
+}
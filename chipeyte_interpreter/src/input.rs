@@ -0,0 +1,117 @@
+use crate::interface::{Controllable, UserAction};
+use std::collections::VecDeque;
+
+/// User actions waiting to be applied to the controller.
+///
+/// Events are applied in order at the start of each frame. A key pressed and released within the
+/// same batch would never be seen by the program, so the release and everything after it is held
+/// back until the next frame.
+pub struct InputQueue {
+    events: VecDeque<UserAction>,
+}
+
+impl InputQueue {
+    pub fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn extend(&mut self, events: Vec<UserAction>) {
+        self.events.extend(events);
+    }
+
+    /// Applies the key events for this frame to `controller`, returning the other actions for the
+    /// caller to handle.
+    pub fn apply(&mut self, controller: &mut dyn Controllable) -> Vec<UserAction> {
+        let mut pressed = Vec::new();
+        let mut actions = Vec::new();
+
+        while let Some(action) = self.events.front() {
+            if let UserAction::KeyUp(key) = action {
+                if pressed.contains(key) {
+                    break;
+                }
+            }
+
+            match self.events.pop_front().unwrap() {
+                UserAction::KeyDown(key) => {
+                    controller.press_key(key);
+                    pressed.push(key);
+                }
+                UserAction::KeyUp(key) => controller.release_key(key),
+                action => actions.push(action),
+            }
+        }
+
+        actions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::Controller;
+
+    #[test]
+    fn apply_delivers_every_event_in_order() {
+        let mut queue = InputQueue::new();
+        let mut controller = Controller::new();
+
+        controller.press_key(1);
+        queue.extend(vec![
+            UserAction::KeyDown(2),
+            UserAction::KeyUp(1),
+            UserAction::KeyDown(3),
+        ]);
+
+        assert!(queue.apply(&mut controller).is_empty());
+        assert!(queue.events.is_empty());
+        assert!(!controller.is_pressed(1));
+        assert!(controller.is_pressed(2));
+        assert!(controller.is_pressed(3));
+    }
+
+    #[test]
+    fn apply_holds_a_quick_tap_for_a_frame() {
+        let mut queue = InputQueue::new();
+        let mut controller = Controller::new();
+
+        queue.extend(vec![
+            UserAction::KeyDown(5),
+            UserAction::KeyUp(5),
+            UserAction::KeyDown(6),
+        ]);
+
+        queue.apply(&mut controller);
+        assert!(controller.is_pressed(5));
+        assert!(!controller.is_pressed(6));
+
+        queue.apply(&mut controller);
+        assert!(!controller.is_pressed(5));
+        assert!(controller.is_pressed(6));
+    }
+
+    #[test]
+    fn apply_returns_actions_other_than_keys() {
+        let mut queue = InputQueue::new();
+        let mut controller = Controller::new();
+
+        queue.extend(vec![
+            UserAction::CyclePalette,
+            UserAction::KeyDown(1),
+            UserAction::UnmappedKey("Q".to_string()),
+            UserAction::Quit,
+        ]);
+
+        assert_eq!(
+            queue.apply(&mut controller),
+            vec![
+                UserAction::CyclePalette,
+                UserAction::UnmappedKey("Q".to_string()),
+                UserAction::Quit
+            ]
+        );
+        assert!(controller.is_pressed(1));
+    }
+}
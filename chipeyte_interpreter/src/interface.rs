@@ -1,41 +1,133 @@
+use crate::errors::ChipeyteError;
+use crate::postprocess::Frame;
+use crate::sound::AudioFrame;
 use std::collections::HashSet;
+use std::fmt::Display;
+use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum UserAction {
     Quit,
-    KeyDown(Option<u8>),
-    KeyUp(Option<u8>),
+    KeyDown(u8),
+    KeyUp(u8),
+    /// A host key without a Chip-8 key bound to it was pressed.
+    UnmappedKey(String),
+    /// Switch the screen to its next colour palette.
+    CyclePalette,
+    ToggleFullscreen,
+    /// Present the last frame again, e.g. after the window was resized.
+    Redraw,
+    /// Pause or resume emulation.
+    Pause,
+    /// Reload the program and restart it.
+    Reset,
+    /// Run a single frame and pause.
+    FrameAdvance,
+    SpeedUp,
+    SlowDown,
+    /// Run frames back to back instead of at 60 Hz.
+    ToggleTurbo,
+}
+
+/// Emulator state shown by front ends, e.g. in the window title.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RunState {
+    pub paused: bool,
+    pub turbo: bool,
+    /// Emulation speed in percent of the configured clock speed.
+    pub speed: u32,
+    pub waiting_for_key: bool,
+}
+
+impl Default for RunState {
+    fn default() -> Self {
+        Self {
+            paused: false,
+            turbo: false,
+            speed: 100,
+            waiting_for_key: false,
+        }
+    }
+}
+
+impl Display for RunState {
+    /// Lists what differs from normal running, e.g. `paused, 150%`, or nothing at all.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut parts = Vec::new();
+
+        if self.paused {
+            parts.push("paused".to_string());
+        }
+        if self.speed != 100 {
+            parts.push(format!("{}%", self.speed));
+        }
+        if self.turbo {
+            parts.push("turbo".to_string());
+        }
+        if self.waiting_for_key {
+            parts.push("waiting for key".to_string());
+        }
+
+        write!(f, "{}", parts.join(", "))
+    }
+}
+
+// INPUT
+
+/// A source of user input.
+pub trait InputSource {
+    /// Returns the action of every event received since the last poll, oldest first.
+    fn poll_events(&mut self) -> Vec<UserAction>;
 }
 
 // SCREEN
 
-#[derive(Clone)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Color(pub u8, pub u8, pub u8);
 
-pub trait Drawable {
-    fn clear(&mut self);
+impl FromStr for Color {
+    type Err = ChipeyteError;
+
+    /// Parses a hex colour string such as `#00ff00` or `00ff00`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.trim().trim_start_matches('#');
+
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(ChipeyteError::BadColor(s.to_string()));
+        }
+
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
 
-    fn add_pixel(&mut self, x: u8, y: u8);
+        Ok(Color(channel(0), channel(2), channel(4)))
+    }
+}
+
+/// A front end presenting the post-processed display.
+///
+/// Screens without palettes or windows can ignore the presentation commands.
+pub trait Drawable {
+    fn render(&mut self, frame: &Frame);
 
-    fn remove_pixel(&mut self, x: u8, y: u8);
+    fn redraw(&mut self) {}
 
-    fn has_pixel(&self, x: u8, y: u8) -> bool;
+    fn cycle_palette(&mut self) {}
 
-    fn render(&mut self);
+    fn toggle_fullscreen(&mut self) {}
 
-    fn poll_events(&mut self) -> Option<UserAction>;
+    /// Called whenever the emulator state changes.
+    fn show_state(&mut self, _state: &RunState) {}
 
-    fn get_pixels(&self) -> HashSet<(u8, u8)>;
+    /// Called when a host key without a Chip-8 key bound to it was pressed.
+    fn show_unmapped_key(&mut self, _name: &str) {}
 }
 
 // AUDIO
 
+/// A speaker driven by emulated time rather than wall-clock time.
 pub trait Audible {
-    fn play_sound(&self);
-
-    fn stop_sound(&self);
-
-    fn is_playing(&self) -> bool;
+    /// Plays the sound of the next emulated frame. Called exactly once per emulated frame, so a
+    /// beep lasts as many frames as the sound timer was set to.
+    fn play_frame(&mut self, frame: &AudioFrame);
 }
 
 // CONTROLLER
@@ -47,43 +139,76 @@ pub trait Controllable {
 
     fn is_pressed(&self, key: u8) -> bool;
 
-    fn get_pressed_key(&mut self) -> Option<u8>;
+    /// Called by `Fx0A` every time it executes while waiting. The first call starts the wait and
+    /// the wait completes, returning the key, once a pressed key has been released.
+    fn wait_for_key(&mut self) -> Option<u8>;
+
+    /// Whether the program is blocked on `Fx0A`.
+    fn is_waiting_for_key(&self) -> bool;
+
+    /// Abandons a wait for a key, e.g. when the program is reset.
+    fn cancel_wait(&mut self);
 }
 
 pub struct Controller {
     pressed_keys: HashSet<u8>,
+    waiting: bool,
+    /// The first key released since the current wait began.
+    released_key: Option<u8>,
 }
 
 impl Controller {
     pub fn new() -> Self {
         Controller {
             pressed_keys: HashSet::new(),
+            waiting: false,
+            released_key: None,
         }
     }
 }
 
+impl Default for Controller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Controllable for Controller {
     fn press_key(&mut self, key: u8) {
         self.pressed_keys.insert(key);
     }
 
     fn release_key(&mut self, key: u8) {
-        self.pressed_keys.remove(&key);
+        let was_pressed = self.pressed_keys.remove(&key);
+
+        if was_pressed && self.waiting && self.released_key.is_none() {
+            self.released_key = Some(key);
+        }
     }
 
     fn is_pressed(&self, key: u8) -> bool {
         self.pressed_keys.contains(&key)
     }
 
-    fn get_pressed_key(&mut self) -> Option<u8> {
-        if self.pressed_keys.is_empty() {
-            None
-        } else {
-            let keys = self.pressed_keys.iter().cloned().collect::<Vec<u8>>();
-            let key = keys.first().unwrap();
-            self.pressed_keys.remove(key);
-            Some(*key)
+    fn wait_for_key(&mut self) -> Option<u8> {
+        if !self.waiting {
+            self.waiting = true;
+            self.released_key = None;
+            return None;
         }
+
+        let key = self.released_key.take();
+        self.waiting = key.is_none();
+        key
+    }
+
+    fn is_waiting_for_key(&self) -> bool {
+        self.waiting
+    }
+
+    fn cancel_wait(&mut self) {
+        self.waiting = false;
+        self.released_key = None;
     }
 }
 
@@ -91,41 +216,98 @@ impl Controllable for Controller {
 mod tests {
     use super::*;
 
+    #[test]
+    fn color_parses_hex_strings() {
+        assert_eq!("#00ff80".parse::<Color>().unwrap(), Color(0, 255, 128));
+        assert_eq!("FFB000".parse::<Color>().unwrap(), Color(255, 176, 0));
+    }
+
+    #[test]
+    fn color_rejects_malformed_hex_strings() {
+        assert!("#00ff8".parse::<Color>().is_err());
+        assert!("#00gg00".parse::<Color>().is_err());
+    }
+
+    #[test]
+    fn run_state_lists_what_differs_from_normal_running() {
+        assert_eq!(RunState::default().to_string(), "");
+
+        let state = RunState {
+            paused: true,
+            speed: 150,
+            ..RunState::default()
+        };
+
+        assert_eq!(state.to_string(), "paused, 150%");
+    }
+
     #[test]
     fn is_pressed_returns_true_if_key_is_pressed() {
         let mut c = Controller::new();
         c.press_key(1);
-        assert_eq!(c.is_pressed(1), true);
+        assert!(c.is_pressed(1));
     }
 
     #[test]
     fn is_pressed_returns_false_if_key_is_not_pressed() {
         let mut c = Controller::new();
         c.press_key(2);
-        assert_eq!(c.is_pressed(1), false);
+        assert!(!c.is_pressed(1));
     }
 
     #[test]
     fn release_key_releases_the_key() {
         let mut c = Controller::new();
         c.press_key(1);
-        assert_eq!(c.is_pressed(1), true);
+        assert!(c.is_pressed(1));
         c.release_key(1);
-        assert_eq!(c.is_pressed(1), false);
+        assert!(!c.is_pressed(1));
     }
 
     #[test]
-    fn get_pressed_key_returns_none_if_no_keys_are_pressed() {
+    fn wait_for_key_waits_until_a_key_is_released() {
         let mut c = Controller::new();
-        assert_eq!(c.get_pressed_key(), None);
+        assert_eq!(c.wait_for_key(), None);
+        assert!(c.is_waiting_for_key());
+
+        c.press_key(1);
+        assert_eq!(c.wait_for_key(), None);
+
+        c.release_key(1);
+        assert_eq!(c.wait_for_key(), Some(1));
+        assert!(!c.is_waiting_for_key());
     }
 
     #[test]
-    fn get_pressed_key_releases_the_key_and_returns_it() {
+    fn wait_for_key_ignores_releases_before_the_wait() {
         let mut c = Controller::new();
         c.press_key(1);
-        let key = c.get_pressed_key().unwrap();
-        assert_eq!(key, 1);
-        assert_eq!(c.is_pressed(key), false);
+        c.release_key(1);
+
+        assert_eq!(c.wait_for_key(), None);
+        assert_eq!(c.wait_for_key(), None);
+    }
+
+    #[test]
+    fn wait_for_key_returns_the_first_key_released() {
+        let mut c = Controller::new();
+        c.wait_for_key();
+
+        c.press_key(7);
+        c.press_key(3);
+        c.release_key(7);
+        c.release_key(3);
+
+        assert_eq!(c.wait_for_key(), Some(7));
+    }
+
+    #[test]
+    fn releasing_a_key_that_is_not_pressed_does_not_end_the_wait() {
+        let mut c = Controller::new();
+        c.wait_for_key();
+
+        c.release_key(2);
+
+        assert_eq!(c.wait_for_key(), None);
     }
 }
@@ -1,104 +1,415 @@
-use cpu::{CPU, PROGRAM_START};
-use interface::{Audible, Controllable, Drawable, UserAction};
+use coverage::Coverage;
+use cpu::{Step, CPU};
+use errors::ChipeyteError;
+use framebuffer::FrameBuffer;
+use input::InputQueue;
+use interface::{Audible, Controllable, Drawable, InputSource, RunState, UserAction};
 use memory::Memory;
 use operations::Ops;
+use postprocess::{Filter, PostProcessor};
+use profile::Profile;
+use quirks::Quirks;
+use rng::Rng;
+use sound::AudioFrame;
 use std::{
     fmt::Display,
     thread,
     time::{Duration, SystemTime},
 };
+use timing::Timing;
+use trace::{TraceWriter, Tracer};
+
+pub use cpu::PROGRAM_START;
+
+/// Duration of a single 60 Hz frame.
+const FRAME_DURATION: Duration = Duration::new(0, 16_666_667);
+
+/// Steps and bounds of the emulation speed, in percent of the configured clock speed.
+const SPEED_STEP: u32 = 25;
+const MIN_SPEED: u32 = 25;
+const MAX_SPEED: u32 = 1000;
+
+/// The backend used to execute a program.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Engine {
+    /// Fetches, decodes and executes a single instruction per dispatch.
+    Interpreter,
+
+    /// Translates straight-line runs of instructions into cached blocks of decoded operations,
+    /// executing a whole block per dispatch.
+    BasicBlock,
+}
 
 pub struct Config {
+    /// Time budget per instruction, which determines how many instructions run per frame.
     pub clock_speed: Option<Duration>,
+    /// Instructions run per frame, overriding `clock_speed` when set.
+    pub instructions_per_frame: Option<u32>,
+    pub engine: Engine,
+    /// Seed for the random number generator used by `RND`, seeded from the clock when `None`.
+    pub seed: Option<u64>,
+    /// Interpreter behaviours the program depends on.
+    pub quirks: Quirks,
+    /// Address the program is loaded at and started from.
+    pub load_address: u16,
+    /// Post-processing applied to the display before it is presented.
+    pub filter: Filter,
+    /// How long instructions take. `Timing::CosmacVip` ignores `clock_speed` and
+    /// `instructions_per_frame`.
+    pub timing: Timing,
 }
 
 impl Config {
     pub fn new(clock_speed: Option<Duration>) -> Self {
-        Self { clock_speed }
+        Self {
+            clock_speed,
+            instructions_per_frame: None,
+            engine: Engine::Interpreter,
+            seed: None,
+            quirks: Quirks::default(),
+            load_address: PROGRAM_START,
+            filter: Filter::None,
+            timing: Timing::Fixed,
+        }
     }
+}
 
-    pub fn default() -> Self {
+impl Default for Config {
+    fn default() -> Self {
         Self::new(None)
     }
 }
 
+/// Whether the program is still running after a frame.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Status {
+    Running,
+    /// Blocked on `Fx0A` until a key is released. Timers keep running while waiting.
+    WaitingForKey,
+    Halted,
+}
+
 pub struct ChipeyteInterpreter {
     cpu: CPU,
     memory: Memory,
-    clock_speed: Duration,
+    frame_buffer: FrameBuffer,
+    post_processor: PostProcessor,
+    instructions_per_frame: u32,
+    /// Instructions per frame at 100% speed.
+    base_instructions_per_frame: u32,
+    speed: u32,
+    quirks: Quirks,
+    timing: Timing,
+    /// Machine cycles into the current frame under `Timing::CosmacVip`, carried over from the last
+    /// frame when an instruction ran past its end.
+    elapsed: u32,
+    load_address: u16,
+    engine: Engine,
+    seed: Option<u64>,
+    /// The loaded program, kept to be reloaded on reset.
+    program: Vec<u8>,
+    paused: bool,
+    turbo: bool,
+    /// The sound of the last frame run.
+    audio_frame: AudioFrame,
+    trace: Option<TraceWriter>,
 }
 
 impl ChipeyteInterpreter {
     pub fn new(config: Config) -> Self {
+        let clock_speed = config.clock_speed.unwrap_or(Duration::new(0, 1_500_000));
+        let instructions_per_frame = config
+            .instructions_per_frame
+            .unwrap_or_else(|| instructions_per_frame(clock_speed))
+            .max(1);
+
         Self {
-            cpu: CPU::new(PROGRAM_START),
+            cpu: new_cpu(&config),
             memory: Memory::new(),
-            clock_speed: config.clock_speed.unwrap_or(Duration::new(0, 1_500_000)),
+            frame_buffer: FrameBuffer::new(),
+            post_processor: PostProcessor::new(config.filter),
+            instructions_per_frame,
+            base_instructions_per_frame: instructions_per_frame,
+            speed: 100,
+            quirks: config.quirks,
+            timing: config.timing,
+            elapsed: 0,
+            load_address: config.load_address,
+            engine: config.engine,
+            seed: config.seed,
+            program: Vec::new(),
+            paused: false,
+            turbo: false,
+            audio_frame: AudioFrame::default(),
+            trace: None,
         }
     }
 
-    pub fn run(
+    pub fn load(&mut self, program: &[u8]) {
+        self.program = program.to_vec();
+        self.memory.load_program(self.load_address.into(), program);
+    }
+
+    /// Writes bytes to memory after loading, such as the platform choice that some test ROMs read
+    /// from 0x1FF instead of asking for it.
+    pub fn poke(&mut self, address: u16, bytes: &[u8]) {
+        self.memory.load_program(address.into(), bytes);
+    }
+
+    /// Writes a trace of the instructions executed from now on, frame by frame.
+    pub fn trace_to(&mut self, writer: TraceWriter) {
+        self.cpu.trace(Tracer::new(writer.window));
+        self.trace = Some(writer);
+    }
+
+    /// Counts the instructions executed from now on, see `profile`.
+    pub fn start_profiling(&mut self) {
+        self.cpu.start_profiling(Profile::new(self.load_address));
+    }
+
+    /// What the program spent its instructions on while profiling.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.cpu.profile()
+    }
+
+    /// Records the memory executed, read and written from now on, see `coverage`.
+    pub fn start_coverage(&mut self) {
+        self.cpu.start_coverage(Coverage::new());
+    }
+
+    /// The memory the program touched while recording coverage.
+    pub fn coverage(&self) -> Option<&Coverage> {
+        self.cpu.coverage()
+    }
+
+    /// Restarts the loaded program on a freshly initialised CPU, memory and display.
+    pub fn reset(&mut self) {
+        let profile = self.cpu.take_profile();
+        let coverage = self.cpu.take_coverage();
+
+        self.cpu = new_cpu(&Config {
+            engine: self.engine,
+            seed: self.seed,
+            quirks: self.quirks,
+            load_address: self.load_address,
+            timing: self.timing,
+            ..Config::default()
+        });
+        self.elapsed = 0;
+        self.memory = Memory::new();
+        self.memory
+            .load_program(self.load_address.into(), &self.program);
+        self.frame_buffer = FrameBuffer::new();
+        self.post_processor = PostProcessor::new(self.post_processor.filter());
+
+        if let Some(writer) = &self.trace {
+            self.cpu.trace(Tracer::new(writer.window));
+        }
+
+        if let Some(mut profile) = profile {
+            profile.restart();
+            self.cpu.start_profiling(profile);
+        }
+
+        if let Some(coverage) = coverage {
+            self.cpu.start_coverage(coverage);
+        }
+    }
+
+    /// Sets the emulation speed in percent of the configured clock speed, which scales the number
+    /// of instructions or machine cycles run per frame.
+    pub fn set_speed(&mut self, percent: u32) {
+        self.speed = percent.clamp(MIN_SPEED, MAX_SPEED);
+        self.instructions_per_frame = (self.base_instructions_per_frame * self.speed / 100).max(1);
+    }
+
+    pub fn speed(&self) -> u32 {
+        self.speed
+    }
+
+    /// The state shown to the user, given the controller the program reads its keys from.
+    pub fn state(&self, controller: &dyn Controllable) -> RunState {
+        RunState {
+            paused: self.paused,
+            turbo: self.turbo,
+            speed: self.speed,
+            waiting_for_key: controller.is_waiting_for_key(),
+        }
+    }
+
+    pub fn frame_buffer(&self) -> &FrameBuffer {
+        &self.frame_buffer
+    }
+
+    /// Runs one 60 Hz frame worth of instructions and then decrements the timers.
+    pub fn run_frame(
         &mut self,
-        screen: &mut dyn Drawable,
-        speaker: &dyn Audible,
         controller: &mut dyn Controllable,
-        program: &Vec<u8>,
-    ) {
-        let timer_duration = Duration::new(0, 16_700_000);
+    ) -> Result<Status, ChipeyteError> {
+        let status = self.run_instructions(controller);
 
-        let mut timer_clock = SystemTime::now();
+        if let Some(profile) = self.cpu.profile_mut() {
+            profile.frames += 1;
+        }
 
-        self.memory.load_program(PROGRAM_START.into(), program);
+        if let Some(writer) = &mut self.trace {
+            writer
+                .write(&self.cpu.take_trace())
+                .map_err(|e| ChipeyteError::TraceFailed(e.to_string()))?;
+        }
 
-        'running: loop {
-            let start_time = SystemTime::now();
+        status
+    }
 
-            match screen.poll_events() {
-                Some(UserAction::Quit) => break 'running,
-                Some(UserAction::KeyDown(Some(key))) => controller.press_key(key),
-                Some(UserAction::KeyUp(Some(key))) => controller.release_key(key),
-                _ => {}
-            };
+    fn run_instructions(
+        &mut self,
+        controller: &mut dyn Controllable,
+    ) -> Result<Status, ChipeyteError> {
+        let mut executed = 0;
+        let cycles_per_frame = self.timing.cycles_per_frame() * self.speed / 100;
 
-            match self.cpu.tick(&mut self.memory, screen, controller) {
-                Ok((_pc, Ops::UNKNOWN(_x))) => {
+        while match self.timing {
+            Timing::Fixed => executed < self.instructions_per_frame,
+            Timing::CosmacVip => self.elapsed < cycles_per_frame,
+        } {
+            let cycles = self.cpu.cycles;
+            let step = self
+                .cpu
+                .step(&mut self.memory, &mut self.frame_buffer, controller)?;
+            self.elapsed += (self.cpu.cycles - cycles) as u32;
+
+            match step {
+                Step {
+                    pc: _pc,
+                    op: Ops::UNKNOWN(_x),
+                    ..
+                } => {
                     #[cfg(feature = "logging")]
                     log::warn!("Reached unknown operation {:04x?} at {:04x?}", _x, _pc);
-                    break 'running;
+                    return Ok(Status::Halted);
                 }
-                Ok((_pc, _op)) => {
-                    #[cfg(feature = "logging")]
-                    log::debug!("{:04x?}: {:?}", _pc, _op);
-                }
-                Err(e) => {
-                    panic!("Something went wrong: {:?}", e);
+                Step { op: Ops::EXIT, .. } => return Ok(Status::Halted),
+                // The VIP waits out the frame for the vertical blank interrupt and draws after it
+                Step {
+                    op: Ops::DRW(_, _, _),
+                    ..
+                } if self.timing == Timing::CosmacVip => {
+                    self.elapsed = cycles_per_frame + self.cpu.last_cycles;
+                    break;
                 }
-            };
-
-            if self.cpu.registers.st > 0 && !speaker.is_playing() {
-                speaker.play_sound();
-            } else if self.cpu.registers.st < 1 && speaker.is_playing() {
-                speaker.stop_sound();
+                Step {
+                    op: Ops::DRW(_, _, _),
+                    ..
+                } if self.quirks.display_wait => break,
+                Step {
+                    op: Ops::LDK(_), ..
+                } if controller.is_waiting_for_key() => break,
+                Step { executed: n, .. } => executed += n,
             }
+        }
 
-            if let Ok(elapsed) = timer_clock.elapsed() {
-                if elapsed > timer_duration {
-                    if self.cpu.registers.dt > 0 {
-                        self.cpu.registers.dt -= 1;
-                    }
+        self.elapsed = self.elapsed.saturating_sub(cycles_per_frame);
+
+        if self.cpu.registers.dt > 0 {
+            self.cpu.registers.dt -= 1;
+        }
+
+        self.audio_frame = AudioFrame {
+            sounding: self.cpu.registers.st > 0,
+            pattern: None,
+        };
 
-                    if self.cpu.registers.st > 0 {
-                        self.cpu.registers.st -= 1;
+        if self.cpu.registers.st > 0 {
+            self.cpu.registers.st -= 1;
+        }
+
+        if controller.is_waiting_for_key() {
+            Ok(Status::WaitingForKey)
+        } else {
+            Ok(Status::Running)
+        }
+    }
+
+    /// The sound of the last frame run.
+    pub fn audio_frame(&self) -> &AudioFrame {
+        &self.audio_frame
+    }
+
+    /// Presents the display if it changed since it was last presented.
+    pub fn present(&mut self, screen: &mut dyn Drawable) {
+        if self.frame_buffer.is_dirty() || self.post_processor.is_animating() {
+            screen.render(self.post_processor.process(&self.frame_buffer));
+            self.frame_buffer.mark_clean();
+        }
+    }
+
+    /// Runs the program until the user quits or it halts, in real time unless in turbo mode.
+    pub fn run(
+        &mut self,
+        screen: &mut dyn Drawable,
+        input: &mut dyn InputSource,
+        speaker: &mut dyn Audible,
+        controller: &mut dyn Controllable,
+        program: &[u8],
+    ) -> Result<(), ChipeyteError> {
+        self.load(program);
+        let mut pending_input = InputQueue::new();
+        let mut shown_state = None;
+
+        'running: loop {
+            let start_time = SystemTime::now();
+            let mut advance = false;
+
+            pending_input.extend(input.poll_events());
+
+            for action in pending_input.apply(controller) {
+                match action {
+                    UserAction::Quit => break 'running,
+                    UserAction::CyclePalette => screen.cycle_palette(),
+                    UserAction::ToggleFullscreen => screen.toggle_fullscreen(),
+                    UserAction::Redraw => screen.redraw(),
+                    UserAction::Pause => self.paused = !self.paused,
+                    UserAction::FrameAdvance => {
+                        self.paused = true;
+                        advance = true;
+                    }
+                    UserAction::Reset => {
+                        self.reset();
+                        controller.cancel_wait();
                     }
+                    UserAction::SpeedUp => self.set_speed(self.speed + SPEED_STEP),
+                    UserAction::SlowDown => self.set_speed(self.speed.saturating_sub(SPEED_STEP)),
+                    UserAction::ToggleTurbo => self.turbo = !self.turbo,
+                    UserAction::UnmappedKey(name) => screen.show_unmapped_key(&name),
+                    _ => {}
+                }
+            }
 
-                    timer_clock = SystemTime::now();
+            if !self.paused || advance {
+                if self.run_frame(controller)? == Status::Halted {
+                    break 'running;
                 }
+
+                speaker.play_frame(&self.audio_frame);
+            }
+
+            let state = self.state(controller);
+
+            if shown_state != Some(state) {
+                screen.show_state(&state);
+                shown_state = Some(state);
+            }
+
+            self.present(screen);
+
+            if self.turbo {
+                continue;
             }
 
             match start_time.elapsed() {
                 Ok(elapsed) => {
-                    if elapsed < self.clock_speed {
-                        thread::sleep(self.clock_speed - elapsed);
+                    if elapsed < FRAME_DURATION {
+                        thread::sleep(FRAME_DURATION - elapsed);
                     }
                 }
                 Err(e) => {
@@ -107,18 +418,466 @@ impl ChipeyteInterpreter {
                 }
             }
         }
+
+        Ok(())
     }
 }
 
+fn new_cpu(config: &Config) -> CPU {
+    CPU::new(
+        config.load_address,
+        config.engine,
+        config.seed.map(Rng::new).unwrap_or_else(Rng::from_time),
+        config.quirks,
+        config.timing,
+    )
+}
+
+fn instructions_per_frame(clock_speed: Duration) -> u32 {
+    let nanos = clock_speed.as_nanos().max(1);
+
+    (FRAME_DURATION.as_nanos() / nanos).max(1) as u32
+}
+
 impl Display for ChipeyteInterpreter {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}\n{}", self.memory, self.cpu)
     }
 }
 
+pub mod analysis;
+pub mod coverage;
 mod cpu;
+pub mod disassembler;
 pub mod errors;
+pub mod framebuffer;
+mod input;
 pub mod interface;
 mod memory;
 mod operations;
+pub mod postprocess;
+pub mod profile;
+pub mod quirks;
+mod rng;
+pub mod sound;
+pub mod timing;
+pub mod trace;
 mod types;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use interface::Controller;
+    use postprocess::{DecayCurve, Frame};
+    use std::{cell::RefCell, io, rc::Rc};
+    use trace::{TraceFormat, TraceWindow, Trigger};
+
+    struct RecordingScreen {
+        frames: Vec<Frame>,
+        states: Vec<RunState>,
+    }
+
+    impl RecordingScreen {
+        fn new() -> Self {
+            Self {
+                frames: Vec::new(),
+                states: Vec::new(),
+            }
+        }
+    }
+
+    impl Drawable for RecordingScreen {
+        fn render(&mut self, frame: &Frame) {
+            self.frames.push(frame.clone());
+        }
+
+        fn show_state(&mut self, state: &RunState) {
+            self.states.push(*state);
+        }
+    }
+
+    /// Plays back one batch of actions per poll.
+    struct ScriptedInput {
+        polls: Vec<Vec<UserAction>>,
+    }
+
+    impl InputSource for ScriptedInput {
+        fn poll_events(&mut self) -> Vec<UserAction> {
+            if self.polls.is_empty() {
+                return Vec::new();
+            }
+
+            self.polls.remove(0)
+        }
+    }
+
+    struct RecordingSpeaker {
+        frames: Vec<AudioFrame>,
+    }
+
+    impl Audible for RecordingSpeaker {
+        fn play_frame(&mut self, frame: &AudioFrame) {
+            self.frames.push(*frame);
+        }
+    }
+
+    /// A trace output that can be read back while the interpreter holds it.
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for SharedBuffer {
+        fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(bytes)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    // 0x200: LD I, 0x100
+    // 0x202: DRW V0, V1, 5
+    // 0x204: ADD V0, 1
+    // 0x206: JP 0x202
+    const SPRITE_LOOP: [u8; 8] = [0xa1, 0x00, 0xd0, 0x15, 0x70, 0x01, 0x12, 0x02];
+
+    #[test]
+    fn run_frame_writes_the_trace_window() {
+        let mut interpreter = ChipeyteInterpreter::new(Config {
+            instructions_per_frame: Some(10),
+            ..Config::default()
+        });
+        let buffer = SharedBuffer::default();
+        interpreter.trace_to(TraceWriter::new(
+            Box::new(buffer.clone()),
+            TraceFormat::Text,
+            TraceWindow {
+                start: Some(Trigger::Pc(0x204, 0x204)),
+                stop: Some(Trigger::Cycle(4)),
+            },
+        ));
+        interpreter.load(&SPRITE_LOOP);
+
+        interpreter.run_frame(&mut Controller::new()).unwrap();
+
+        let trace = String::from_utf8(buffer.0.borrow().clone()).unwrap();
+        assert_eq!(
+            trace.lines().collect::<Vec<&str>>(),
+            vec![
+                "       2 0204 7001 ADD V0, 0x01         V0=01",
+                "       3 0206 1202 JP 0x202",
+                "       4 0202 d015 DRW V0, V1, 5        VF=01",
+            ]
+        );
+    }
+
+    #[test]
+    fn run_frame_decrements_timers() {
+        let mut interpreter = ChipeyteInterpreter::new(Config::default());
+        let mut controller = Controller::new();
+        interpreter.load(&SPRITE_LOOP);
+        interpreter.cpu.registers.dt = 2;
+        interpreter.cpu.registers.st = 1;
+
+        interpreter.run_frame(&mut controller).unwrap();
+
+        assert_eq!(interpreter.cpu.registers.dt, 1);
+        assert_eq!(interpreter.cpu.registers.st, 0);
+    }
+
+    #[test]
+    fn loads_and_starts_the_program_at_the_load_address() {
+        let mut interpreter = ChipeyteInterpreter::new(Config {
+            load_address: 0x300,
+            ..Config::default()
+        });
+        let mut controller = Controller::new();
+
+        // 0x300: LD V0, 0x2a
+        // 0x302: JP 0x302
+        interpreter.load(&[0x60, 0x2a, 0x13, 0x02]);
+        interpreter.run_frame(&mut controller).unwrap();
+
+        assert_eq!(interpreter.cpu.registers.v0, 0x2a);
+        assert_eq!(interpreter.cpu.registers.pc, 0x302);
+    }
+
+    #[test]
+    fn run_applies_scripted_input_until_quit() {
+        let mut interpreter = ChipeyteInterpreter::new(Config::default());
+        let mut screen = RecordingScreen::new();
+        let mut input = ScriptedInput {
+            polls: vec![vec![UserAction::KeyDown(5)], vec![], vec![UserAction::Quit]],
+        };
+        let mut controller = Controller::new();
+
+        interpreter
+            .run(
+                &mut screen,
+                &mut input,
+                &mut RecordingSpeaker { frames: Vec::new() },
+                &mut controller,
+                &SPRITE_LOOP,
+            )
+            .unwrap();
+
+        assert_eq!(screen.frames.len(), 2);
+        assert!(controller.is_pressed(5));
+    }
+
+    #[test]
+    fn beeps_last_exactly_as_many_frames_as_the_sound_timer() {
+        let mut interpreter = ChipeyteInterpreter::new(Config::default());
+        let mut speaker = RecordingSpeaker { frames: Vec::new() };
+        let mut input = ScriptedInput {
+            polls: vec![
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![],
+                vec![UserAction::Quit],
+            ],
+        };
+
+        // 0x200: LD V0, 3
+        // 0x202: LD ST, V0
+        // 0x204: JP 0x204
+        interpreter
+            .run(
+                &mut RecordingScreen::new(),
+                &mut input,
+                &mut speaker,
+                &mut Controller::new(),
+                &[0x60, 0x03, 0xf0, 0x18, 0x12, 0x04],
+            )
+            .unwrap();
+
+        let sounding = speaker
+            .frames
+            .iter()
+            .map(|frame| frame.sounding)
+            .collect::<Vec<bool>>();
+
+        assert_eq!(sounding, vec![true, true, true, false, false]);
+    }
+
+    #[test]
+    fn run_pauses_and_advances_a_single_frame() {
+        let mut interpreter = ChipeyteInterpreter::new(Config::default());
+        let mut screen = RecordingScreen::new();
+        let mut input = ScriptedInput {
+            polls: vec![
+                vec![UserAction::Pause],
+                vec![],
+                vec![UserAction::FrameAdvance],
+                vec![UserAction::Quit],
+            ],
+        };
+        let mut controller = Controller::new();
+
+        interpreter
+            .run(
+                &mut screen,
+                &mut input,
+                &mut RecordingSpeaker { frames: Vec::new() },
+                &mut controller,
+                &SPRITE_LOOP,
+            )
+            .unwrap();
+
+        assert_eq!(interpreter.cpu.counter, interpreter.instructions_per_frame);
+        assert_eq!(
+            screen.states,
+            vec![RunState {
+                paused: true,
+                ..RunState::default()
+            }]
+        );
+    }
+
+    #[test]
+    fn reset_reloads_the_program() {
+        let mut interpreter = ChipeyteInterpreter::new(Config::default());
+        let mut controller = Controller::new();
+        interpreter.load(&SPRITE_LOOP);
+
+        interpreter.run_frame(&mut controller).unwrap();
+        interpreter.memory.set(0x300, 0xff);
+        interpreter.reset();
+
+        assert_eq!(interpreter.cpu.registers.pc, PROGRAM_START);
+        assert_eq!(interpreter.cpu.registers.v0, 0);
+        assert_eq!(interpreter.memory.get(0x300), 0);
+        assert_eq!(interpreter.memory.get(0x200), 0xa1);
+        assert_eq!(interpreter.frame_buffer.lit_pixels().count(), 0);
+    }
+
+    #[test]
+    fn speed_scales_instructions_per_frame() {
+        let mut interpreter =
+            ChipeyteInterpreter::new(Config::new(Some(Duration::new(0, 1_000_000))));
+        assert_eq!(interpreter.instructions_per_frame, 16);
+
+        interpreter.set_speed(150);
+        assert_eq!(interpreter.instructions_per_frame, 24);
+
+        interpreter.set_speed(0);
+        assert_eq!(interpreter.speed(), MIN_SPEED);
+        assert_eq!(interpreter.instructions_per_frame, 4);
+    }
+
+    #[test]
+    fn run_frame_halts_on_zero_instruction() {
+        let mut interpreter = ChipeyteInterpreter::new(Config::default());
+        let mut controller = Controller::new();
+        interpreter.load(&[0x60, 0x01]);
+
+        assert_eq!(
+            interpreter.run_frame(&mut controller).unwrap(),
+            Status::Halted
+        );
+    }
+
+    #[test]
+    fn wait_for_key_completes_on_release_while_timers_run() {
+        let mut interpreter = ChipeyteInterpreter::new(Config::default());
+        let mut controller = Controller::new();
+        // 0x200: LD V0, K
+        // 0x202: JP 0x202
+        interpreter.load(&[0xf0, 0x0a, 0x12, 0x02]);
+        interpreter.cpu.registers.dt = 10;
+
+        assert_eq!(
+            interpreter.run_frame(&mut controller).unwrap(),
+            Status::WaitingForKey
+        );
+
+        controller.press_key(0xb);
+        assert_eq!(
+            interpreter.run_frame(&mut controller).unwrap(),
+            Status::WaitingForKey
+        );
+
+        controller.release_key(0xb);
+        assert_eq!(
+            interpreter.run_frame(&mut controller).unwrap(),
+            Status::Running
+        );
+
+        assert_eq!(interpreter.cpu.registers.v0, 0xb);
+        assert_eq!(interpreter.cpu.registers.dt, 7);
+    }
+
+    #[test]
+    fn vblank_wait_draws_one_sprite_per_frame() {
+        let mut interpreter = ChipeyteInterpreter::new(Config {
+            quirks: Quirks {
+                display_wait: true,
+                ..Quirks::default()
+            },
+            ..Config::default()
+        });
+        let mut controller = Controller::new();
+        interpreter.load(&SPRITE_LOOP);
+
+        for _ in 0..3 {
+            interpreter.run_frame(&mut controller).unwrap();
+        }
+
+        assert_eq!(interpreter.cpu.registers.v0, 2);
+    }
+
+    #[test]
+    fn without_vblank_wait_a_frame_runs_many_sprites() {
+        let mut interpreter = ChipeyteInterpreter::new(Config::default());
+        let mut controller = Controller::new();
+        interpreter.load(&SPRITE_LOOP);
+
+        interpreter.run_frame(&mut controller).unwrap();
+
+        assert!(interpreter.cpu.registers.v0 > 1);
+    }
+
+    fn vip_interpreter(program: &[u8]) -> ChipeyteInterpreter {
+        let mut interpreter = ChipeyteInterpreter::new(Config {
+            timing: Timing::CosmacVip,
+            ..Config::default()
+        });
+        interpreter.load(program);
+        interpreter
+    }
+
+    #[test]
+    fn vip_timing_runs_fewer_slow_instructions_per_frame() {
+        // 0x200: ADD V0, 1
+        // 0x202: JP 0x200
+        let mut fast = vip_interpreter(&[0x70, 0x01, 0x12, 0x00]);
+        // 0x200: ADD V0, V1
+        // 0x202: JP 0x200
+        let mut slow = vip_interpreter(&[0x80, 0x14, 0x12, 0x00]);
+
+        fast.run_frame(&mut Controller::new()).unwrap();
+        slow.run_frame(&mut Controller::new()).unwrap();
+
+        // ADD V0, 1 takes 50 cycles, ADD V0, V1 84 and the jump 52
+        assert_eq!(fast.cpu.counter, 36);
+        assert_eq!(slow.cpu.counter, 27);
+        assert!(fast.elapsed < 52);
+    }
+
+    #[test]
+    fn vip_timing_draws_one_sprite_per_frame_after_the_vertical_blank() {
+        let mut interpreter = vip_interpreter(&SPRITE_LOOP);
+        let mut controller = Controller::new();
+
+        interpreter.run_frame(&mut controller).unwrap();
+
+        // The aligned 5 row sprite takes from the next frame
+        assert_eq!(interpreter.elapsed, 40 + 26 + 5 * 38);
+
+        for _ in 0..2 {
+            interpreter.run_frame(&mut controller).unwrap();
+        }
+
+        assert_eq!(interpreter.cpu.registers.v0, 2);
+    }
+
+    #[test]
+    fn present_keeps_rendering_while_phosphor_fades() {
+        let mut interpreter = ChipeyteInterpreter::new(Config {
+            filter: Filter::Phosphor {
+                frames: 2,
+                curve: DecayCurve::Linear,
+            },
+            quirks: Quirks {
+                display_wait: true,
+                ..Quirks::default()
+            },
+            ..Config::default()
+        });
+        let mut screen = RecordingScreen::new();
+        let mut controller = Controller::new();
+
+        // 0x200: LD I, 0x100
+        // 0x202: DRW V0, V0, 1
+        // 0x204: DRW V0, V0, 1
+        // 0x206: JP 0x206
+        interpreter.load(&[0xa1, 0x00, 0xd0, 0x01, 0xd0, 0x01, 0x12, 0x06]);
+        interpreter.frame_buffer.mark_clean();
+
+        for _ in 0..5 {
+            interpreter.run_frame(&mut controller).unwrap();
+            interpreter.present(&mut screen);
+        }
+
+        let intensities = screen
+            .frames
+            .iter()
+            .map(|frame| frame.intensity(0, 0))
+            .collect::<Vec<u8>>();
+
+        // Drawn in the first frame, erased in the second and then faded out without redrawing
+        assert_eq!(intensities, vec![255, 170, 85, 0]);
+    }
+}
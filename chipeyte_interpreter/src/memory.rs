@@ -136,7 +136,7 @@ impl Memory {
         self.memory[0x01F4] = 0b10000000;
     }
 
-    pub fn load_program(&mut self, program_start_address: usize, program: &Vec<u8>) {
+    pub fn load_program(&mut self, program_start_address: usize, program: &[u8]) {
         for (offset, byte) in program.iter().enumerate() {
             self.set(program_start_address + offset, *byte);
         }
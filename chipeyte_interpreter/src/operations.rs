@@ -2,15 +2,53 @@ use crate::{
     cpu::registers::Registers,
     cpu::{INSTRUCTION_LENGTH, PROGRAM_START},
     errors::ChipeyteError,
+    framebuffer::{FrameBuffer, Resolution},
     interface::Controllable,
-    interface::Drawable,
     memory::Memory,
+    quirks::{Quirks, Variant},
+    rng::Rng,
     types::*,
 };
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::fmt;
 
 const STACK_ENTRY_LENGTH: u8 = 2;
 
+/// Groups of operations, by the kind of work they do.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Category {
+    /// Jumps, calls, returns and machine code calls.
+    Flow,
+    /// Skips depending on registers.
+    Conditional,
+    /// Register loads, arithmetic, logic and random numbers.
+    Arithmetic,
+    /// Operations on I and memory.
+    Memory,
+    Display,
+    /// Key skips and waits.
+    Input,
+    /// The delay timer.
+    Timer,
+    /// The sound timer and XO-CHIP audio.
+    Sound,
+}
+
+impl Category {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Category::Flow => "flow",
+            Category::Conditional => "conditional",
+            Category::Arithmetic => "arithmetic",
+            Category::Memory => "memory",
+            Category::Display => "display",
+            Category::Input => "input",
+            Category::Timer => "timer",
+            Category::Sound => "sound",
+        }
+    }
+}
+
+#[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Ops {
     UNKNOWN(u16),
@@ -130,8 +168,9 @@ pub enum Ops {
     ///
     /// Op code: `8xy6`
     ///
-    /// Stores the least significant bit of `Vx` in Vf and then shifts `Vx` to the right by 1.
-    SHR(V),
+    /// Stores the least significant bit of `Vx` in Vf and then shifts `Vx` to the right by 1. With
+    /// the `shift_vy` quirk `Vy` is shifted into `Vx` instead.
+    SHR(V, V),
 
     /// SUBN `Vx`, `Vy`
     ///
@@ -144,8 +183,9 @@ pub enum Ops {
     ///
     /// Op code: `8xyE`
     ///
-    /// Stores the most significant bit of `Vx` in Vf and then shifts `Vx` to the left by 1.
-    SHL(V),
+    /// Stores the most significant bit of `Vx` in Vf and then shifts `Vx` to the left by 1. With
+    /// the `shift_vy` quirk `Vy` is shifted into `Vx` instead.
+    SHL(V, V),
 
     /// SNE `Vx`, `Vy`
     ///
@@ -213,7 +253,8 @@ pub enum Ops {
     ///
     /// Op code: `Fx0A`
     ///
-    /// A key press is awaited, and then stored in `Vx`.
+    /// Waits for a key to be pressed and released, and then stores it in `Vx`. As on the COSMAC
+    /// VIP the wait completes on release, and the timers keep running meanwhile.
     LDK(V),
 
     /// LD DT, `Vx`
@@ -276,6 +317,118 @@ pub enum Ops {
     ///
     /// Fills V0 to `Vx` (including `Vx`) with values from memory starting at address I.
     LDVI(V),
+
+    /// SCD `n`
+    ///
+    /// Op code: `00Cn`
+    ///
+    /// SUPER-CHIP: Scroll the display down by `n` pixels. Not yet implemented.
+    SCD(Nibble),
+
+    /// SCU `n`
+    ///
+    /// Op code: `00Dn`
+    ///
+    /// XO-CHIP: Scroll the display up by `n` pixels. Not yet implemented.
+    SCU(Nibble),
+
+    /// SCR
+    ///
+    /// Op code: `00FB`
+    ///
+    /// SUPER-CHIP: Scroll the display right by 4 pixels. Not yet implemented.
+    SCR,
+
+    /// SCL
+    ///
+    /// Op code: `00FC`
+    ///
+    /// SUPER-CHIP: Scroll the display left by 4 pixels. Not yet implemented.
+    SCL,
+
+    /// EXIT
+    ///
+    /// Op code: `00FD`
+    ///
+    /// SUPER-CHIP: Exit the interpreter.
+    EXIT,
+
+    /// LOW
+    ///
+    /// Op code: `00FE`
+    ///
+    /// SUPER-CHIP: Switch to the 64 x 32 display.
+    LOW,
+
+    /// HIGH
+    ///
+    /// Op code: `00FF`
+    ///
+    /// SUPER-CHIP: Switch to the 128 x 64 display.
+    HIGH,
+
+    /// SAVE `Vx` - `Vy`
+    ///
+    /// Op code: `5xy2`
+    ///
+    /// XO-CHIP: Store `Vx` to `Vy` in memory starting at address I. Not yet implemented.
+    SAVE(V, V),
+
+    /// LOAD `Vx` - `Vy`
+    ///
+    /// Op code: `5xy3`
+    ///
+    /// XO-CHIP: Load `Vx` to `Vy` from memory starting at address I. Not yet implemented.
+    LOAD(V, V),
+
+    /// LD I, `nnnn`
+    ///
+    /// Op code: `F000 nnnn`
+    ///
+    /// XO-CHIP: Set I to the 16-bit address in the following two bytes. Not yet implemented.
+    LDIL,
+
+    /// PLANE `n`
+    ///
+    /// Op code: `Fn01`
+    ///
+    /// XO-CHIP: Select the bit planes `n` affected by drawing and clearing.
+    PLANE(Nibble),
+
+    /// AUDIO
+    ///
+    /// Op code: `F002`
+    ///
+    /// XO-CHIP: Load the 16 byte audio pattern at I. Not yet implemented.
+    AUDIO,
+
+    /// LD HF, `Vx`
+    ///
+    /// Op code: `Fx30`
+    ///
+    /// SUPER-CHIP: Set I to the large sprite for the digit `Vx`. Not yet implemented.
+    LDHF(V),
+
+    /// PITCH `Vx`
+    ///
+    /// Op code: `Fx3A`
+    ///
+    /// XO-CHIP: Set the playback rate of the audio pattern to `Vx`. Not yet implemented.
+    PITCH(V),
+
+    /// LD R, `Vx`
+    ///
+    /// Op code: `Fx75`
+    ///
+    /// SUPER-CHIP: Store V0 to `Vx` in the RPL user flags. Not yet implemented.
+    LDRV(V),
+
+    /// LD `Vx`, R
+    ///
+    /// Op code: `Fx85`
+    ///
+    /// SUPER-CHIP: Fill V0 to `Vx` from the RPL user flags. Not yet implemented.
+    LDVR(V),
 }
 
 impl Ops {
@@ -283,10 +436,12 @@ impl Ops {
         &self,
         registers: &mut Registers,
         memory: &mut Memory,
-        screen: &mut dyn Drawable,
+        frame_buffer: &mut FrameBuffer,
         controller: &mut dyn Controllable,
+        rng: &mut Rng,
+        quirks: &Quirks,
     ) -> Result<(), ChipeyteError> {
-        match &*self {
+        match self {
             Ops::UNKNOWN(op) => Err(ChipeyteError::OpFailed(
                 *self,
                 format!("Unknown operation: {:04x?}", op),
@@ -295,7 +450,7 @@ impl Ops {
             Ops::SYS(_) => Ok(()),
 
             Ops::CLS => {
-                screen.clear();
+                frame_buffer.clear();
                 Ok(())
             }
 
@@ -381,21 +536,39 @@ impl Ops {
                 let x = registers.get_data_register_value(*vx)?;
                 let y = registers.get_data_register_value(*vy)?;
 
-                registers.set_data_register_value(*vx, x | y)
+                registers.set_data_register_value(*vx, x | y)?;
+
+                if quirks.vf_reset {
+                    registers.vf = 0;
+                }
+
+                Ok(())
             }
 
             Ops::AND(vx, vy) => {
                 let x = registers.get_data_register_value(*vx)?;
                 let y = registers.get_data_register_value(*vy)?;
 
-                registers.set_data_register_value(*vx, x & y)
+                registers.set_data_register_value(*vx, x & y)?;
+
+                if quirks.vf_reset {
+                    registers.vf = 0;
+                }
+
+                Ok(())
             }
 
             Ops::XOR(vx, vy) => {
                 let x = registers.get_data_register_value(*vx)?;
                 let y = registers.get_data_register_value(*vy)?;
 
-                registers.set_data_register_value(*vx, x ^ y)
+                registers.set_data_register_value(*vx, x ^ y)?;
+
+                if quirks.vf_reset {
+                    registers.vf = 0;
+                }
+
+                Ok(())
             }
 
             Ops::ADDV(vx, vy) => {
@@ -425,8 +598,9 @@ impl Ops {
                 }
             }
 
-            Ops::SHR(vx) => {
-                let x = registers.get_data_register_value(*vx)?;
+            Ops::SHR(vx, vy) => {
+                let x =
+                    registers.get_data_register_value(if quirks.shift_vy { *vy } else { *vx })?;
 
                 let least_significant_bit = x & 0b0000_0001;
 
@@ -448,10 +622,11 @@ impl Ops {
                 }
             }
 
-            Ops::SHL(vx) => {
-                let x = registers.get_data_register_value(*vx)?;
+            Ops::SHL(vx, vy) => {
+                let x =
+                    registers.get_data_register_value(if quirks.shift_vy { *vy } else { *vx })?;
 
-                let most_significant_bit = x & 0b1000_0000;
+                let most_significant_bit = x >> 7;
 
                 registers.vf = most_significant_bit;
 
@@ -483,9 +658,14 @@ impl Ops {
             }
 
             Ops::JPV0(value) => {
-                let result = *value + registers.v0 as u16;
+                let offset = if quirks.jump_vx {
+                    registers.get_data_register_value((*value >> 8) as u8)?
+                } else {
+                    registers.v0
+                };
+                let result = *value + offset as u16;
 
-                if result < PROGRAM_START || result > 0x0fff {
+                if !(PROGRAM_START..=0x0fff).contains(&result) {
                     return Err(ChipeyteError::OpFailed(
                         *self,
                         format!(
@@ -499,50 +679,24 @@ impl Ops {
                 Ok(())
             }
 
-            Ops::RND(vx, value) => {
-                let rand = random_number(u8::MAX.into()) as u8;
-
-                registers.set_data_register_value(*vx, value & rand)
-            }
+            Ops::RND(vx, value) => registers.set_data_register_value(*vx, value & rng.next_byte()),
 
             Ops::DRW(vx, vy, n) => {
-                let base_x = registers.get_data_register_value(*vx)?;
-                let base_y = registers.get_data_register_value(*vy)?;
-                let sprite_addr = registers.i;
-
-                let bytes = (0..(*n as u16))
-                    .map(move |offset| {
-                        let addr = (sprite_addr + offset) as usize;
-                        memory.get(addr)
-                    })
-                    .collect::<Vec<u8>>();
-
-                let mut has_removed_pixel = false;
-
-                for (y_offset, byte) in bytes.iter().enumerate() {
-                    let mut mask = 0b1000_0000;
-
-                    for x_offset in 0..8 {
-                        let is_one = (byte & mask) > 0;
-                        if is_one {
-                            let x = ((base_x as u32 + x_offset as u32) % 64) as u8;
-                            let y = ((base_y as u32 + (y_offset as u32)) % 32) as u8;
-
-                            if screen.has_pixel(x, y) {
-                                screen.remove_pixel(x, y);
-                                has_removed_pixel = true;
-                            } else {
-                                screen.add_pixel(x, y);
-                            }
-                        }
+                let x = registers.get_data_register_value(*vx)?;
+                let y = registers.get_data_register_value(*vy)?;
+                let length = *n as u16 * frame_buffer.selected_plane_count() as u16;
 
-                        mask >>= 1;
-                    }
-                }
+                let sprite = (0..length)
+                    .map(|offset| memory.get((registers.i + offset) as usize))
+                    .collect::<Vec<u8>>();
 
-                registers.vf = if has_removed_pixel { 1 } else { 0 };
+                let collision = if quirks.clip {
+                    frame_buffer.draw_sprite_clipped(x, y, &sprite)
+                } else {
+                    frame_buffer.draw_sprite(x, y, &sprite)
+                };
 
-                screen.render();
+                registers.vf = if collision { 1 } else { 0 };
 
                 Ok(())
             }
@@ -565,7 +719,7 @@ impl Ops {
 
             Ops::LDVDT(vx) => registers.set_data_register_value(*vx, registers.dt),
 
-            Ops::LDK(vx) => match controller.get_pressed_key() {
+            Ops::LDK(vx) => match controller.wait_for_key() {
                 Some(key) => registers.set_data_register_value(*vx, key),
                 None => {
                     registers.pc -= INSTRUCTION_LENGTH;
@@ -626,77 +780,232 @@ impl Ops {
             Ops::LDIV(vx) => {
                 let base_addr = registers.i as usize;
 
+                if base_addr + *vx as usize > 0x0fff {
+                    return Err(ChipeyteError::OpFailed(
+                        *self,
+                        format!(
+                            "Memory addresses '{:04x?}'-'{:04x?}' are out-of-bounds",
+                            base_addr,
+                            base_addr + *vx as usize
+                        ),
+                    ));
+                }
+
                 for reg in 0..=*vx {
                     let value = registers.get_data_register_value(reg)?;
                     memory.set(base_addr + reg as usize, value);
                 }
+
+                // I stays within the 4 KB address space when the last byte is at 0xFFF
+                if quirks.memory_increment {
+                    registers.i = (registers.i + *vx as u16 + 1) & 0x0fff;
+                }
                 Ok(())
             }
 
             Ops::LDVI(vx) => {
                 let base_addr = registers.i as usize;
 
+                if base_addr + *vx as usize > 0x0fff {
+                    return Err(ChipeyteError::OpFailed(
+                        *self,
+                        format!(
+                            "Memory addresses '{:04x?}'-'{:04x?}' are out-of-bounds",
+                            base_addr,
+                            base_addr + *vx as usize
+                        ),
+                    ));
+                }
+
                 for reg in 0..=*vx {
                     let value = memory.get(base_addr + reg as usize);
                     registers.set_data_register_value(reg, value)?;
                 }
+
+                // I stays within the 4 KB address space when the last byte is at 0xFFF
+                if quirks.memory_increment {
+                    registers.i = (registers.i + *vx as u16 + 1) & 0x0fff;
+                }
                 Ok(())
             }
-        }
-    }
-}
 
-fn random_number(max_val: u32) -> u32 {
-    let nanos = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .subsec_nanos();
+            Ops::EXIT => Ok(()),
 
-    return nanos % max_val;
-}
+            Ops::LOW => {
+                frame_buffer.set_resolution(Resolution::Low);
+                Ok(())
+            }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::{
-        interface::{Controllable, UserAction},
-        Drawable,
-    };
-    use std::collections::HashSet;
+            Ops::HIGH => {
+                frame_buffer.set_resolution(Resolution::High);
+                Ok(())
+            }
+
+            Ops::PLANE(mask) => {
+                frame_buffer.select_planes(*mask);
+                Ok(())
+            }
 
-    struct MockScreen {
-        pixels: HashSet<(u8, u8)>,
+            Ops::SCD(_)
+            | Ops::SCU(_)
+            | Ops::SCR
+            | Ops::SCL
+            | Ops::SAVE(_, _)
+            | Ops::LOAD(_, _)
+            | Ops::LDIL
+            | Ops::AUDIO
+            | Ops::LDHF(_)
+            | Ops::PITCH(_)
+            | Ops::LDRV(_)
+            | Ops::LDVR(_) => Err(ChipeyteError::OpNotImplemented(*self)),
+        }
     }
 
-    impl MockScreen {
-        pub fn init() -> Self {
-            MockScreen {
-                pixels: HashSet::new(),
+    /// The kind of work the operation does.
+    pub fn category(&self) -> Category {
+        match self {
+            Ops::UNKNOWN(_)
+            | Ops::SYS(_)
+            | Ops::RET
+            | Ops::JP(_)
+            | Ops::CALL(_)
+            | Ops::JPV0(_)
+            | Ops::EXIT => Category::Flow,
+            Ops::SE(_, _) | Ops::SNE(_, _) | Ops::SEV(_, _) | Ops::SNEV(_, _) => {
+                Category::Conditional
             }
+            Ops::LD(_, _)
+            | Ops::ADD(_, _)
+            | Ops::LDV(_, _)
+            | Ops::OR(_, _)
+            | Ops::AND(_, _)
+            | Ops::XOR(_, _)
+            | Ops::ADDV(_, _)
+            | Ops::SUB(_, _)
+            | Ops::SHR(_, _)
+            | Ops::SUBN(_, _)
+            | Ops::SHL(_, _)
+            | Ops::RND(_, _) => Category::Arithmetic,
+            Ops::LDI(_)
+            | Ops::ADDI(_)
+            | Ops::LDF(_)
+            | Ops::LDHF(_)
+            | Ops::LDB(_)
+            | Ops::LDIV(_)
+            | Ops::LDVI(_)
+            | Ops::SAVE(_, _)
+            | Ops::LOAD(_, _)
+            | Ops::LDIL
+            | Ops::LDRV(_)
+            | Ops::LDVR(_) => Category::Memory,
+            Ops::CLS
+            | Ops::DRW(_, _, _)
+            | Ops::SCD(_)
+            | Ops::SCU(_)
+            | Ops::SCR
+            | Ops::SCL
+            | Ops::LOW
+            | Ops::HIGH
+            | Ops::PLANE(_) => Category::Display,
+            Ops::SKP(_) | Ops::SKNP(_) | Ops::LDK(_) => Category::Input,
+            Ops::LDVDT(_) | Ops::LDDT(_) => Category::Timer,
+            Ops::LDST(_) | Ops::AUDIO | Ops::PITCH(_) => Category::Sound,
         }
     }
 
-    impl crate::Drawable for MockScreen {
-        fn get_pixels(&self) -> HashSet<(u8, u8)> {
-            self.pixels.clone()
-        }
-        fn clear(&mut self) {
-            self.pixels.clear();
+    /// The first variant that has the operation.
+    ///
+    /// `Dxy0` draws a 16 x 16 sprite in SUPER-CHIP, but nothing at all in Chip-8, so it counts as
+    /// a SUPER-CHIP operation.
+    pub fn variant(&self) -> Variant {
+        match self {
+            Ops::SCD(_)
+            | Ops::SCR
+            | Ops::SCL
+            | Ops::EXIT
+            | Ops::LOW
+            | Ops::HIGH
+            | Ops::LDHF(_)
+            | Ops::LDRV(_)
+            | Ops::LDVR(_)
+            | Ops::DRW(_, _, 0) => Variant::Schip,
+            Ops::SCU(_)
+            | Ops::SAVE(_, _)
+            | Ops::LOAD(_, _)
+            | Ops::LDIL
+            | Ops::PLANE(_)
+            | Ops::AUDIO
+            | Ops::PITCH(_) => Variant::Xochip,
+            _ => Variant::Chip8,
         }
-        fn add_pixel(&mut self, x: u8, y: u8) {
-            self.pixels.insert((x, y));
-        }
-        fn remove_pixel(&mut self, x: u8, y: u8) {
-            self.pixels.remove(&(x, y));
-        }
-        fn has_pixel(&self, x: u8, y: u8) -> bool {
-            self.pixels.contains(&(x, y))
-        }
-        fn render(&mut self) {}
-        fn poll_events(&mut self) -> Option<UserAction> {
-            None
+    }
+}
+
+impl fmt::Display for Ops {
+    /// Formats the operation in the mnemonics of Cowgod's Chip-8 technical reference.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Ops::UNKNOWN(opcode) => write!(f, "DW 0x{:04x}", opcode),
+            Ops::SYS(addr) => write!(f, "SYS 0x{:03x}", addr),
+            Ops::CLS => write!(f, "CLS"),
+            Ops::RET => write!(f, "RET"),
+            Ops::JP(addr) => write!(f, "JP 0x{:03x}", addr),
+            Ops::CALL(addr) => write!(f, "CALL 0x{:03x}", addr),
+            Ops::SE(vx, byte) => write!(f, "SE V{:X}, 0x{:02x}", vx, byte),
+            Ops::SNE(vx, byte) => write!(f, "SNE V{:X}, 0x{:02x}", vx, byte),
+            Ops::SEV(vx, vy) => write!(f, "SE V{:X}, V{:X}", vx, vy),
+            Ops::LD(vx, byte) => write!(f, "LD V{:X}, 0x{:02x}", vx, byte),
+            Ops::ADD(vx, byte) => write!(f, "ADD V{:X}, 0x{:02x}", vx, byte),
+            Ops::LDV(vx, vy) => write!(f, "LD V{:X}, V{:X}", vx, vy),
+            Ops::OR(vx, vy) => write!(f, "OR V{:X}, V{:X}", vx, vy),
+            Ops::AND(vx, vy) => write!(f, "AND V{:X}, V{:X}", vx, vy),
+            Ops::XOR(vx, vy) => write!(f, "XOR V{:X}, V{:X}", vx, vy),
+            Ops::ADDV(vx, vy) => write!(f, "ADD V{:X}, V{:X}", vx, vy),
+            Ops::SUB(vx, vy) => write!(f, "SUB V{:X}, V{:X}", vx, vy),
+            Ops::SHR(vx, vy) => write!(f, "SHR V{:X}, V{:X}", vx, vy),
+            Ops::SUBN(vx, vy) => write!(f, "SUBN V{:X}, V{:X}", vx, vy),
+            Ops::SHL(vx, vy) => write!(f, "SHL V{:X}, V{:X}", vx, vy),
+            Ops::SNEV(vx, vy) => write!(f, "SNE V{:X}, V{:X}", vx, vy),
+            Ops::LDI(addr) => write!(f, "LD I, 0x{:03x}", addr),
+            Ops::JPV0(addr) => write!(f, "JP V0, 0x{:03x}", addr),
+            Ops::RND(vx, byte) => write!(f, "RND V{:X}, 0x{:02x}", vx, byte),
+            Ops::DRW(vx, vy, n) => write!(f, "DRW V{:X}, V{:X}, {}", vx, vy, n),
+            Ops::SKP(vx) => write!(f, "SKP V{:X}", vx),
+            Ops::SKNP(vx) => write!(f, "SKNP V{:X}", vx),
+            Ops::LDVDT(vx) => write!(f, "LD V{:X}, DT", vx),
+            Ops::LDK(vx) => write!(f, "LD V{:X}, K", vx),
+            Ops::LDDT(vx) => write!(f, "LD DT, V{:X}", vx),
+            Ops::LDST(vx) => write!(f, "LD ST, V{:X}", vx),
+            Ops::ADDI(vx) => write!(f, "ADD I, V{:X}", vx),
+            Ops::LDF(vx) => write!(f, "LD F, V{:X}", vx),
+            Ops::LDB(vx) => write!(f, "LD B, V{:X}", vx),
+            Ops::LDIV(vx) => write!(f, "LD [I], V{:X}", vx),
+            Ops::LDVI(vx) => write!(f, "LD V{:X}, [I]", vx),
+            Ops::SCD(n) => write!(f, "SCD {}", n),
+            Ops::SCU(n) => write!(f, "SCU {}", n),
+            Ops::SCR => write!(f, "SCR"),
+            Ops::SCL => write!(f, "SCL"),
+            Ops::EXIT => write!(f, "EXIT"),
+            Ops::LOW => write!(f, "LOW"),
+            Ops::HIGH => write!(f, "HIGH"),
+            Ops::SAVE(vx, vy) => write!(f, "SAVE V{:X} - V{:X}", vx, vy),
+            Ops::LOAD(vx, vy) => write!(f, "LOAD V{:X} - V{:X}", vx, vy),
+            Ops::LDIL => write!(f, "LD I, long"),
+            Ops::PLANE(n) => write!(f, "PLANE {}", n),
+            Ops::AUDIO => write!(f, "AUDIO"),
+            Ops::LDHF(vx) => write!(f, "LD HF, V{:X}", vx),
+            Ops::PITCH(vx) => write!(f, "PITCH V{:X}", vx),
+            Ops::LDRV(vx) => write!(f, "LD R, V{:X}", vx),
+            Ops::LDVR(vx) => write!(f, "LD V{:X}, R", vx),
         }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interface::Controllable;
+    use std::collections::HashSet;
 
     struct MockController {
         pressed_keys: HashSet<u8>,
@@ -720,20 +1029,44 @@ mod tests {
         fn is_pressed(&self, key: u8) -> bool {
             self.pressed_keys.contains(&key)
         }
-        fn get_pressed_key(&mut self) -> Option<u8> {
+        fn wait_for_key(&mut self) -> Option<u8> {
             None
         }
+        fn is_waiting_for_key(&self) -> bool {
+            false
+        }
+        fn cancel_wait(&mut self) {}
+    }
+
+    impl Ops {
+        /// Calls the operation with a seeded random number generator and no quirks.
+        fn run(
+            &self,
+            registers: &mut Registers,
+            memory: &mut Memory,
+            screen: &mut FrameBuffer,
+            controller: &mut MockController,
+        ) -> Result<(), ChipeyteError> {
+            self.call(
+                registers,
+                memory,
+                screen,
+                controller,
+                &mut Rng::new(1),
+                &Quirks::default(),
+            )
+        }
     }
 
     #[test]
     fn op_sys_is_ignored() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::SYS(0x0aaa)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers, Registers::new(PROGRAM_START));
@@ -743,36 +1076,35 @@ mod tests {
     #[test]
     fn op_cls_clears_screen() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
-        screen.add_pixel(0, 0);
-        screen.add_pixel(0, 1);
+        screen.draw_sprite(0, 0, &[0b1000_0000, 0b1000_0000]);
 
         Ops::CLS
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
-        assert!(screen.get_pixels().is_empty());
+        assert_eq!(screen.lit_pixels().count(), 0);
     }
 
     #[test]
     fn op_ret_returns() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::CALL(0x0aaa)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.sp, 0x0002);
         assert_eq!(registers.pc, 0x0aaa);
 
         Ops::RET
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(memory.get_u16(0x0002), 0x0200);
@@ -782,12 +1114,12 @@ mod tests {
     #[test]
     fn op_jp_jumps_to_addr() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::JP(0x0aaa)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.pc, 0x0aaa);
@@ -796,12 +1128,12 @@ mod tests {
     #[test]
     fn op_jp_must_be_within_memory_bounds() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         if let Err(ChipeyteError::OpFailed(op, msg)) =
-            Ops::JP(0xf000).call(&mut registers, &mut memory, &mut screen, &mut controller)
+            Ops::JP(0xf000).run(&mut registers, &mut memory, &mut screen, &mut controller)
         {
             assert_eq!(op, Ops::JP(0xf000));
             assert!(msg.contains("out-of-bounds"));
@@ -814,12 +1146,12 @@ mod tests {
     #[test]
     fn op_call_calls_addr() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::CALL(0x0aaa)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.pc, 0x0aaa);
@@ -830,12 +1162,12 @@ mod tests {
     #[test]
     fn op_call_addr_must_be_within_memory_bounds() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         if let Err(ChipeyteError::OpFailed(op, msg)) =
-            Ops::CALL(0xf000).call(&mut registers, &mut memory, &mut screen, &mut controller)
+            Ops::CALL(0xf000).run(&mut registers, &mut memory, &mut screen, &mut controller)
         {
             assert_eq!(op, Ops::CALL(0xf000));
             assert!(msg.contains("out-of-bounds"));
@@ -848,15 +1180,15 @@ mod tests {
     #[test]
     fn op_se_vkk_increments_pc_if_v_equals_kk() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x08, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::SE(0x08, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.sp, 0);
@@ -866,15 +1198,15 @@ mod tests {
     #[test]
     fn op_se_vkk_does_not_increment_pc_if_v_not_equal_to_kk() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x08, 0x84)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::SE(0x08, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.sp, 0);
@@ -884,15 +1216,15 @@ mod tests {
     #[test]
     fn op_sne_vkk_does_increment_pc_if_v_equals_kk() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x08, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::SNE(0x08, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.sp, 0);
@@ -902,15 +1234,15 @@ mod tests {
     #[test]
     fn op_sne_vkk_increments_pc_if_v_not_equal_to_kk() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x08, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::SNE(0x08, 0x84)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.sp, 0);
@@ -920,18 +1252,18 @@ mod tests {
     #[test]
     fn op_se_vxvy_increments_pc_if_vx_equals_vy() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x08, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0a, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::SEV(0x08, 0x0a)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.sp, 0);
@@ -941,18 +1273,18 @@ mod tests {
     #[test]
     fn op_se_vxvy_does_not_increment_pc_if_vx_not_equal_to_vy() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x08, 0x42)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0a, 0x84)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::SE(0x08, 0x0a)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.sp, 0);
@@ -962,12 +1294,12 @@ mod tests {
     #[test]
     fn op_ld_vkk_sets_register_v_to_kk() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x0a, 0x66)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.va, 0x66);
@@ -976,15 +1308,15 @@ mod tests {
     #[test]
     fn op_add_vkk_adds_kk_to_v() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0, 30)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .expect("Failed to set register");
         Ops::ADD(0, 12)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .expect("Failed to add to register");
 
         assert_eq!(registers.v0, 42);
@@ -993,15 +1325,15 @@ mod tests {
     #[test]
     fn op_add_vkk_adds_kk_to_v_no_carry() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0, 200)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .expect("Failed to set register");
         Ops::ADD(0, 200)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .expect("Failed to add to register");
 
         assert_eq!(registers.v0, 144);
@@ -1011,15 +1343,15 @@ mod tests {
     #[test]
     fn op_ld_vxvy_stores_vx_in_vy() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x0b, 0x09)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LDV(0x0a, 0x0b)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.va, 9);
@@ -1028,19 +1360,19 @@ mod tests {
     #[test]
     fn op_or_vx_vy_stores_bitwise_or_in_vx() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x0a, 0b1001_0111)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0b, 0b0110_1001)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         Ops::OR(0x0a, 0x0b)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.va, 0b1111_1111);
@@ -1049,19 +1381,19 @@ mod tests {
     #[test]
     fn op_and_vx_vy_stores_bitwise_and_in_vx() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x0a, 0b1001_0111)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0b, 0b0110_1001)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         Ops::AND(0x0a, 0x0b)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.va, 0b0000_0001);
@@ -1070,19 +1402,19 @@ mod tests {
     #[test]
     fn op_xor_vx_vy_stores_bitwise_xor_in_vx() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x0a, 0b1001_0111)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0b, 0b0110_1001)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         Ops::XOR(0x0a, 0x0b)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.va, 0b1111_1110);
@@ -1091,30 +1423,30 @@ mod tests {
     #[test]
     fn op_add_vx_vy_adds_vy_to_vx_and_sets_carry() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x0a, 0b1111_1111)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0b, 0b111_0000)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         Ops::ADDV(0x0a, 0x0b)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.va, 0b0110_1111);
         assert_eq!(registers.vf, 1);
 
         Ops::LD(0x0c, 0b0000_0011)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         Ops::ADDV(0x0b, 0x0c)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.vb, 0b0111_0011);
@@ -1124,32 +1456,32 @@ mod tests {
     #[test]
     fn op_sub_vx_vy_subtract_vy_from_vx_and_set_not_borrow() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x0a, 7)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0b, 3)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0c, 5)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0d, 9)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         Ops::SUB(0x0a, 0x0b)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.va, 4); // 7 - 3 = 4
         assert_eq!(registers.vf, 1);
 
         Ops::SUB(0x0c, 0x0d)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.vc, 252); // 5 - 9 [(252 + 9) % 256 = 5]  256 = u8::MAX + 1
@@ -1159,32 +1491,32 @@ mod tests {
     #[test]
     fn op_subn_vx_vy_subtract_vx_from_vy_and_set_not_borrow() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x0a, 7)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0b, 10)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0c, 12)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
         Ops::LD(0x0d, 9)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         Ops::SUBN(0x0a, 0x0b)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.va, 3); // 10 - 7 = 3
         assert_eq!(registers.vf, 1);
 
         Ops::SUBN(0x0c, 0x0d)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
         assert_eq!(registers.vc, 253); // 9 - 12 = [(253 + 12) % 256 = 9]
@@ -1193,15 +1525,15 @@ mod tests {
 
     #[test]
     fn op_shr_vx_right_shifts() {
-        let ops = vec![Ops::LD(0x0a, 0b1111_1111), Ops::SHR(0x0a)];
+        let ops = [Ops::LD(0x0a, 0b1111_1111), Ops::SHR(0x0a, 0x0b)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
@@ -1210,25 +1542,25 @@ mod tests {
 
     #[test]
     fn op_shr_vx_stores_least_significant_bit_in_vf() {
-        let instructions = vec![Ops::LD(0x0a, 0b1111_1111), Ops::SHR(0x0a)];
+        let instructions = [Ops::LD(0x0a, 0b1111_1111), Ops::SHR(0x0a, 0x0b)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         instructions.iter().for_each(|instruction| {
             (*instruction)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
         assert_eq!(registers.vf, 1);
 
-        let instructions = vec![Ops::LD(0x0a, 0b0000_1110), Ops::SHR(0x0a)];
+        let instructions = [Ops::LD(0x0a, 0b0000_1110), Ops::SHR(0x0a, 0x0b)];
 
         instructions.iter().for_each(|instruction| {
             (*instruction)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
@@ -1237,15 +1569,15 @@ mod tests {
 
     #[test]
     fn op_shl_vx_left_shifts() {
-        let ops = vec![Ops::LD(0x0a, 0b0111_1111), Ops::SHL(0x0a)];
+        let ops = [Ops::LD(0x0a, 0b0111_1111), Ops::SHL(0x0a, 0x0b)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
@@ -1254,29 +1586,29 @@ mod tests {
 
     #[test]
     fn op_shl_stores_most_significant_bit_in_vf() {
-        let ops = vec![Ops::LD(0x0a, 0b1111_0000), Ops::SHL(0x0a)];
+        let ops = [Ops::LD(0x0a, 0b1111_0000), Ops::SHL(0x0a, 0x0b)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
-        assert_eq!(registers.vf, 0b1000_0000);
+        assert_eq!(registers.vf, 1);
 
-        let ops = vec![Ops::LD(0x0a, 0b0111_0000), Ops::SHL(0x0a)];
+        let ops = [Ops::LD(0x0a, 0b0111_0000), Ops::SHL(0x0a, 0x0b)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
@@ -1285,25 +1617,25 @@ mod tests {
 
     #[test]
     fn op_snev_increments_pc_if_vx_not_equals_vy() {
-        let ops = vec![Ops::LD(0x0a, 42), Ops::LD(0x0b, 42), Ops::SNEV(0x0a, 0x0b)];
+        let ops = [Ops::LD(0x0a, 42), Ops::LD(0x0b, 42), Ops::SNEV(0x0a, 0x0b)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
         assert_eq!(registers.pc, PROGRAM_START);
 
-        let ops = vec![Ops::LD(0x0a, 42), Ops::LD(0x0b, 24), Ops::SNEV(0x0a, 0x0b)];
+        let ops = [Ops::LD(0x0a, 42), Ops::LD(0x0b, 24), Ops::SNEV(0x0a, 0x0b)];
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
@@ -1312,15 +1644,15 @@ mod tests {
 
     #[test]
     fn op_ldi_sets_i_register() {
-        let ops = vec![Ops::LDI(0x0012)];
+        let ops = [Ops::LDI(0x0012)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
@@ -1330,12 +1662,12 @@ mod tests {
     #[test]
     fn op_ldi_addr_must_be_within_memory_bounds() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         if let Err(ChipeyteError::OpFailed(op, msg)) =
-            Ops::LDI(0xf000).call(&mut registers, &mut memory, &mut screen, &mut controller)
+            Ops::LDI(0xf000).run(&mut registers, &mut memory, &mut screen, &mut controller)
         {
             assert_eq!(op, Ops::LDI(0xf000));
             assert!(msg.contains("out-of-bounds"));
@@ -1347,15 +1679,15 @@ mod tests {
 
     #[test]
     fn op_jpv0_jumps_to_nnn_plus_v0() {
-        let ops = vec![Ops::LD(0x00, 0x10), Ops::JPV0(0x0220)];
+        let ops = [Ops::LD(0x00, 0x10), Ops::JPV0(0x0220)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
@@ -1365,15 +1697,15 @@ mod tests {
     #[test]
     fn op_jpv0_returns_error_if_resulting_address_is_out_of_bounds() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x00, 0xff)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
-        match Ops::JPV0(0x0fff).call(&mut registers, &mut memory, &mut screen, &mut controller) {
+        match Ops::JPV0(0x0fff).run(&mut registers, &mut memory, &mut screen, &mut controller) {
             Err(ChipeyteError::OpFailed(Ops::JPV0(0x0fff), msg)) => {
                 assert!(msg.contains("outside of program area"));
             }
@@ -1384,15 +1716,15 @@ mod tests {
     #[test]
     fn op_jpv0_returns_error_if_resulting_address_is_outside_of_program_area() {
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         Ops::LD(0x00, 0xff)
-            .call(&mut registers, &mut memory, &mut screen, &mut controller)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
             .unwrap();
 
-        match Ops::JPV0(0x0000).call(&mut registers, &mut memory, &mut screen, &mut controller) {
+        match Ops::JPV0(0x0000).run(&mut registers, &mut memory, &mut screen, &mut controller) {
             Err(ChipeyteError::OpFailed(Ops::JPV0(0x0000), msg)) => {
                 assert!(msg.contains("outside of program area"));
             }
@@ -1402,15 +1734,15 @@ mod tests {
 
     #[test]
     fn op_rnd_sets_vx_to_a_random_number() {
-        let ops = vec![Ops::RND(0x0c, 0xff)];
+        let ops = [Ops::RND(0x0c, 0xff)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
@@ -1420,9 +1752,9 @@ mod tests {
 
     #[test]
     fn op_ldvdt_sets_the_vx_equal_to_dt() {
-        let ops = vec![Ops::LDVDT(0x0d)];
+        let ops = [Ops::LDVDT(0x0d)];
         let mut memory = Memory::new();
-        let mut screen = MockScreen::init();
+        let mut screen = FrameBuffer::new();
         let mut controller = MockController::new();
         let mut registers = Registers::new(PROGRAM_START);
 
@@ -1430,10 +1762,234 @@ mod tests {
 
         ops.iter().for_each(|op| {
             (*op)
-                .call(&mut registers, &mut memory, &mut screen, &mut controller)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
                 .unwrap();
         });
 
         assert_eq!(registers.vd, 42);
     }
+
+    #[test]
+    fn op_drw_draws_sprite_and_sets_vf_on_collision() {
+        let ops = [
+            Ops::LD(0x0a, 4),
+            Ops::LD(0x0b, 2),
+            Ops::LDF(0x0a),
+            Ops::DRW(0x0a, 0x0b, 5),
+        ];
+        let mut memory = Memory::new();
+        let mut screen = FrameBuffer::new();
+        let mut controller = MockController::new();
+        let mut registers = Registers::new(PROGRAM_START);
+
+        ops.iter().for_each(|op| {
+            (*op)
+                .run(&mut registers, &mut memory, &mut screen, &mut controller)
+                .unwrap();
+        });
+
+        // The sprite for the digit 4 starts with 0b1001_0000
+        assert!(screen.is_lit(4, 2));
+        assert!(!screen.is_lit(5, 2));
+        assert!(screen.is_lit(7, 2));
+        assert_eq!(registers.vf, 0);
+
+        Ops::DRW(0x0a, 0x0b, 5)
+            .run(&mut registers, &mut memory, &mut screen, &mut controller)
+            .unwrap();
+
+        assert_eq!(screen.lit_pixels().count(), 0);
+        assert_eq!(registers.vf, 1);
+    }
+
+    fn run_with_quirks(ops: &[Ops], quirks: Quirks) -> Registers {
+        let mut memory = Memory::new();
+        let mut screen = FrameBuffer::new();
+        let mut controller = MockController::new();
+        let mut rng = Rng::new(1);
+        let mut registers = Registers::new(PROGRAM_START);
+
+        for op in ops {
+            op.call(
+                &mut registers,
+                &mut memory,
+                &mut screen,
+                &mut controller,
+                &mut rng,
+                &quirks,
+            )
+            .unwrap();
+        }
+
+        registers
+    }
+
+    #[test]
+    fn vf_reset_quirk_clears_vf_after_logic_ops() {
+        let ops = [Ops::LD(0x0f, 0x01), Ops::OR(0x0a, 0x0b)];
+
+        let registers = run_with_quirks(&ops, Quirks::default());
+        assert_eq!(registers.vf, 1);
+
+        let registers = run_with_quirks(
+            &ops,
+            Quirks {
+                vf_reset: true,
+                ..Quirks::default()
+            },
+        );
+        assert_eq!(registers.vf, 0);
+    }
+
+    #[test]
+    fn memory_increment_quirk_advances_i_past_the_registers() {
+        let ops = [Ops::LDI(0x300), Ops::LDIV(0x02), Ops::LDVI(0x01)];
+
+        let registers = run_with_quirks(&ops, Quirks::default());
+        assert_eq!(registers.i, 0x300);
+
+        let registers = run_with_quirks(
+            &ops,
+            Quirks {
+                memory_increment: true,
+                ..Quirks::default()
+            },
+        );
+        assert_eq!(registers.i, 0x305);
+    }
+
+    #[test]
+    fn memory_increment_quirk_wraps_i_at_the_top_of_memory() {
+        let quirks = Quirks {
+            memory_increment: true,
+            ..Quirks::default()
+        };
+
+        let registers = run_with_quirks(&[Ops::LDI(0xffe), Ops::LDIV(0x01)], quirks);
+        assert_eq!(registers.i, 0);
+
+        let registers = run_with_quirks(
+            &[Ops::LDI(0xffe), Ops::LDVI(0x01), Ops::DRW(0, 0, 5)],
+            quirks,
+        );
+        assert_eq!(registers.i, 0);
+    }
+
+    #[test]
+    fn storing_and_loading_past_the_top_of_memory_fails() {
+        let mut memory = Memory::new();
+        let mut screen = FrameBuffer::new();
+        let mut controller = MockController::new();
+        let mut registers = Registers::new(PROGRAM_START);
+        registers.i = 0xffe;
+
+        for op in [Ops::LDIV(0x02), Ops::LDVI(0x02)].iter() {
+            let result = op.run(&mut registers, &mut memory, &mut screen, &mut controller);
+
+            assert!(matches!(result, Err(ChipeyteError::OpFailed(_, _))));
+        }
+    }
+
+    #[test]
+    fn shift_vy_quirk_shifts_vy_into_vx() {
+        let ops = [
+            Ops::LD(0x0a, 0b0000_0100),
+            Ops::LD(0x0b, 0b0000_0011),
+            Ops::SHR(0x0a, 0x0b),
+        ];
+
+        let registers = run_with_quirks(&ops, Quirks::default());
+        assert_eq!((registers.va, registers.vf), (0b0000_0010, 0));
+
+        let registers = run_with_quirks(
+            &ops,
+            Quirks {
+                shift_vy: true,
+                ..Quirks::default()
+            },
+        );
+        assert_eq!((registers.va, registers.vf), (0b0000_0001, 1));
+    }
+
+    #[test]
+    fn clip_quirk_drops_sprite_pixels_past_the_edge() {
+        // The sprite for the digit 4 starts with 0b1001_0000, so its fourth pixel lands on x = 1
+        // when wrapped around from x = 62
+        let ops = [
+            Ops::LD(0x0a, 62),
+            Ops::LD(0x0b, 0),
+            Ops::LD(0x0c, 4),
+            Ops::LDF(0x0c),
+            Ops::DRW(0x0a, 0x0b, 5),
+        ];
+
+        for (clip, wrapped) in [(false, true), (true, false)] {
+            let mut memory = Memory::new();
+            let mut frame_buffer = FrameBuffer::new();
+            let mut controller = MockController::new();
+            let mut rng = Rng::new(1);
+            let mut registers = Registers::new(PROGRAM_START);
+            let quirks = Quirks {
+                clip,
+                ..Quirks::default()
+            };
+
+            for op in &ops {
+                op.call(
+                    &mut registers,
+                    &mut memory,
+                    &mut frame_buffer,
+                    &mut controller,
+                    &mut rng,
+                    &quirks,
+                )
+                .unwrap();
+            }
+
+            assert!(frame_buffer.is_lit(62, 0));
+            assert_eq!(frame_buffer.is_lit(1, 0), wrapped);
+        }
+    }
+
+    #[test]
+    fn ops_display_in_cowgod_mnemonics() {
+        let listing = [
+            Ops::UNKNOWN(0xf0ff),
+            Ops::JP(0x2a0),
+            Ops::SHL(0x0a, 0x0b),
+            Ops::DRW(0x01, 0x0f, 5),
+            Ops::LDIV(0x0c),
+        ]
+        .iter()
+        .map(|op| op.to_string())
+        .collect::<Vec<String>>();
+
+        assert_eq!(
+            listing,
+            vec![
+                "DW 0xf0ff",
+                "JP 0x2a0",
+                "SHL VA, VB",
+                "DRW V1, VF, 5",
+                "LD [I], VC"
+            ]
+        );
+    }
+
+    #[test]
+    fn jump_vx_quirk_offsets_by_the_register_in_the_address() {
+        let ops = [Ops::LD(0x00, 0x02), Ops::LD(0x03, 0x04), Ops::JPV0(0x300)];
+
+        let registers = run_with_quirks(&ops, Quirks::default());
+        assert_eq!(registers.pc, 0x302);
+
+        let registers = run_with_quirks(
+            &ops,
+            Quirks {
+                jump_vx: true,
+                ..Quirks::default()
+            },
+        );
+        assert_eq!(registers.pc, 0x304);
+    }
 }
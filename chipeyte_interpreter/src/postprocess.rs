@@ -0,0 +1,283 @@
+use crate::framebuffer::FrameBuffer;
+
+/// How quickly an unlit pixel fades out under the phosphor filter.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DecayCurve {
+    /// Intensity falls by the same amount every frame.
+    Linear,
+
+    /// Intensity halves every `half_life` frames.
+    Exponential { half_life: f32 },
+
+    /// Intensity stays at full brightness for the whole decay, then drops to zero.
+    Hold,
+}
+
+impl DecayCurve {
+    /// Intensity of a pixel `age` frames after it was last lit, fading out over `frames` frames.
+    pub fn intensity(&self, age: u8, frames: u8) -> u8 {
+        if age == 0 {
+            return u8::MAX;
+        }
+
+        if age > frames {
+            return 0;
+        }
+
+        match self {
+            DecayCurve::Linear => {
+                let steps = frames as u32 + 1;
+                (u8::MAX as u32 * (steps - age as u32) / steps) as u8
+            }
+            DecayCurve::Exponential { half_life } => {
+                (u8::MAX as f32 * 0.5f32.powf(age as f32 / half_life.max(f32::EPSILON))) as u8
+            }
+            DecayCurve::Hold => u8::MAX,
+        }
+    }
+}
+
+/// Post-processing applied to the frame buffer before it is presented.
+///
+/// Chip-8 programs move sprites by erasing and redrawing them with XOR, which makes them flicker
+/// when a frame is presented between the two draws.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Filter {
+    /// Presents the frame buffer as is.
+    None,
+
+    /// Emulates phosphor persistence by fading unlit pixels out over `frames` frames.
+    Phosphor { frames: u8, curve: DecayCurve },
+
+    /// Shows a pixel if it was lit in either of the last two frames.
+    Deflicker,
+}
+
+/// A presentable image, holding the lit planes and the intensity of every pixel.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    width: usize,
+    height: usize,
+    colors: Vec<u8>,
+    intensities: Vec<u8>,
+}
+
+impl Frame {
+    fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            colors: vec![0; width * height],
+            intensities: vec![0; width * height],
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the planes lit at (`x`, `y`) as a bitmask, kept while the pixel fades out.
+    pub fn color(&self, x: usize, y: usize) -> u8 {
+        self.colors[y * self.width + x]
+    }
+
+    /// Returns the brightness at (`x`, `y`), from 0 (off) to 255 (fully lit).
+    pub fn intensity(&self, x: usize, y: usize) -> u8 {
+        self.intensities[y * self.width + x]
+    }
+
+    /// Iterates over every visible pixel as `(x, y, color, intensity)`.
+    pub fn pixels(&self) -> impl Iterator<Item = (usize, usize, u8, u8)> + '_ {
+        self.intensities
+            .iter()
+            .enumerate()
+            .filter(|(_, intensity)| **intensity > 0)
+            .map(move |(index, intensity)| {
+                (
+                    index % self.width,
+                    index / self.width,
+                    self.colors[index],
+                    *intensity,
+                )
+            })
+    }
+}
+
+/// Turns frame buffers into presentable frames, keeping the history needed by the filters.
+#[derive(Debug, PartialEq, Clone)]
+pub struct PostProcessor {
+    filter: Filter,
+    frame: Frame,
+    ages: Vec<u8>,
+    previous: Vec<u8>,
+    animating: bool,
+}
+
+impl PostProcessor {
+    pub fn new(filter: Filter) -> Self {
+        let mut processor = Self {
+            filter,
+            frame: Frame::new(0, 0),
+            ages: Vec::new(),
+            previous: Vec::new(),
+            animating: false,
+        };
+        processor.resize(64, 32);
+        processor
+    }
+
+    pub fn filter(&self) -> Filter {
+        self.filter
+    }
+
+    /// Whether the next frame differs from the last one even if the frame buffer does not
+    /// change, as is the case while pixels are fading out.
+    pub fn is_animating(&self) -> bool {
+        self.animating
+    }
+
+    /// The most recently processed frame.
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// Processes the current state of the frame buffer. Expected to be called once per presented
+    /// frame, as the filters age pixels per call.
+    pub fn process(&mut self, frame_buffer: &FrameBuffer) -> &Frame {
+        let (width, height) = (frame_buffer.width(), frame_buffer.height());
+
+        if (width, height) != (self.frame.width, self.frame.height) {
+            self.resize(width, height);
+        }
+
+        self.animating = false;
+
+        for y in 0..height {
+            for x in 0..width {
+                let index = y * width + x;
+                let color = frame_buffer.pixel(x, y);
+
+                let (shown, intensity) = match self.filter {
+                    Filter::None => (color, if color != 0 { u8::MAX } else { 0 }),
+
+                    Filter::Phosphor { frames, curve } => {
+                        if color != 0 {
+                            self.ages[index] = 0;
+                            (color, u8::MAX)
+                        } else {
+                            self.ages[index] = self.ages[index].saturating_add(1);
+                            let intensity = curve.intensity(self.ages[index], frames);
+
+                            self.animating |= intensity > 0;
+
+                            if intensity > 0 {
+                                (self.frame.colors[index], intensity)
+                            } else {
+                                (0, 0)
+                            }
+                        }
+                    }
+
+                    Filter::Deflicker => {
+                        let previous = self.previous[index];
+                        self.previous[index] = color;
+                        self.animating |= color == 0 && previous != 0;
+
+                        let shown = if color != 0 { color } else { previous };
+                        (shown, if shown != 0 { u8::MAX } else { 0 })
+                    }
+                };
+
+                self.frame.colors[index] = shown;
+                self.frame.intensities[index] = intensity;
+            }
+        }
+
+        &self.frame
+    }
+
+    fn resize(&mut self, width: usize, height: usize) {
+        self.frame = Frame::new(width, height);
+        self.ages = vec![u8::MAX; width * height];
+        self.previous = vec![0; width * height];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lit_at_origin() -> FrameBuffer {
+        let mut fb = FrameBuffer::new();
+        fb.draw_sprite(0, 0, &[0b1000_0000]);
+        fb
+    }
+
+    #[test]
+    fn no_filter_presents_the_frame_buffer_as_is() {
+        let mut processor = PostProcessor::new(Filter::None);
+
+        assert_eq!(processor.process(&lit_at_origin()).intensity(0, 0), 255);
+        assert_eq!(processor.process(&FrameBuffer::new()).intensity(0, 0), 0);
+        assert!(!processor.is_animating());
+    }
+
+    #[test]
+    fn phosphor_fades_pixels_out_linearly() {
+        let mut processor = PostProcessor::new(Filter::Phosphor {
+            frames: 3,
+            curve: DecayCurve::Linear,
+        });
+        let blank = FrameBuffer::new();
+
+        processor.process(&lit_at_origin());
+
+        let intensities = (0..4)
+            .map(|_| processor.process(&blank).intensity(0, 0))
+            .collect::<Vec<u8>>();
+
+        assert_eq!(intensities, vec![191, 127, 63, 0]);
+        assert!(!processor.is_animating());
+    }
+
+    #[test]
+    fn phosphor_keeps_the_color_while_fading() {
+        let mut processor = PostProcessor::new(Filter::Phosphor {
+            frames: 2,
+            curve: DecayCurve::Hold,
+        });
+
+        processor.process(&lit_at_origin());
+        let frame = processor.process(&FrameBuffer::new());
+
+        assert_eq!(frame.color(0, 0), 1);
+        assert_eq!(frame.intensity(0, 0), 255);
+        assert!(processor.is_animating());
+    }
+
+    #[test]
+    fn exponential_decay_halves_every_half_life() {
+        let curve = DecayCurve::Exponential { half_life: 1.0 };
+
+        assert_eq!(curve.intensity(1, 8), 127);
+        assert_eq!(curve.intensity(2, 8), 63);
+        assert_eq!(curve.intensity(9, 8), 0);
+    }
+
+    #[test]
+    fn deflicker_ors_the_last_two_frames() {
+        let mut processor = PostProcessor::new(Filter::Deflicker);
+        let blank = FrameBuffer::new();
+
+        processor.process(&lit_at_origin());
+
+        assert_eq!(processor.process(&blank).intensity(0, 0), 255);
+        assert!(processor.is_animating());
+        assert_eq!(processor.process(&blank).intensity(0, 0), 0);
+        assert!(!processor.is_animating());
+    }
+}
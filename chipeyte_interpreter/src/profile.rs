@@ -0,0 +1,245 @@
+use crate::operations::{Category, Ops};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Size of the address space, one count per address.
+const ADDRESSES: usize = 0x1000;
+
+/// How often each instruction ran, gathered while a program runs.
+///
+/// Instructions are attributed to the stack of subroutines they ran in, which is followed through
+/// `CALL` and `RET` starting from the entry point of the program.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Profile {
+    /// Entry point of the program, at the bottom of every stack.
+    pub origin: u16,
+    /// Frames run while profiling.
+    pub frames: u64,
+    /// Instructions executed while profiling.
+    pub instructions: u64,
+    counts: Vec<u64>,
+    ops: Vec<Option<Ops>>,
+    categories: BTreeMap<Category, u64>,
+    calls: BTreeMap<u16, u64>,
+    /// Instructions executed by each stack of subroutine entry points, innermost last.
+    stacks: HashMap<Vec<u16>, u64>,
+    stack: Vec<u16>,
+    /// Instructions executed by the current stack since it was last counted in `stacks`.
+    pending: u64,
+}
+
+/// The time spent in a subroutine.
+#[derive(Debug, PartialEq)]
+pub struct Subroutine {
+    pub entry: u16,
+    pub calls: u64,
+    /// Instructions executed in the subroutine and the subroutines it called.
+    pub inclusive: u64,
+    /// Instructions executed in the subroutine itself.
+    pub exclusive: u64,
+}
+
+impl Profile {
+    pub fn new(origin: u16) -> Profile {
+        Profile {
+            origin,
+            frames: 0,
+            instructions: 0,
+            counts: vec![0; ADDRESSES],
+            ops: vec![None; ADDRESSES],
+            categories: BTreeMap::new(),
+            calls: BTreeMap::new(),
+            stacks: HashMap::new(),
+            stack: vec![origin],
+            pending: 0,
+        }
+    }
+
+    /// Counts an executed instruction, following the stack through calls and returns.
+    pub(crate) fn record(&mut self, pc: u16, op: &Ops) {
+        let address = pc as usize % ADDRESSES;
+
+        self.instructions += 1;
+        self.counts[address] += 1;
+        self.ops[address] = Some(*op);
+        *self.categories.entry(op.category()).or_insert(0) += 1;
+        self.pending += 1;
+
+        match op {
+            Ops::CALL(target) => {
+                self.flush();
+                self.stack.push(*target);
+                *self.calls.entry(*target).or_insert(0) += 1;
+            }
+            // Programs that drop return addresses never return to the entry point
+            Ops::RET if self.stack.len() > 1 => {
+                self.flush();
+                self.stack.pop();
+            }
+            _ => {}
+        }
+    }
+
+    /// Starts again from the entry point, as after a reset, keeping the counts.
+    pub(crate) fn restart(&mut self) {
+        self.flush();
+        self.stack = vec![self.origin];
+    }
+
+    /// Executed addresses with their counts and the operation last executed there, hottest first.
+    pub fn hot_addresses(&self) -> Vec<(u16, u64, Ops)> {
+        let mut addresses: Vec<(u16, u64, Ops)> = self
+            .counts
+            .iter()
+            .zip(&self.ops)
+            .enumerate()
+            .filter_map(|(address, (count, op))| op.map(|op| (address as u16, *count, op)))
+            .collect();
+
+        addresses.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        addresses
+    }
+
+    /// Instructions executed per category, most executed first.
+    pub fn categories(&self) -> Vec<(Category, u64)> {
+        let mut categories: Vec<(Category, u64)> =
+            self.categories.iter().map(|(c, n)| (*c, *n)).collect();
+
+        categories.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        categories
+    }
+
+    /// The entry point and every subroutine called, by most inclusive instructions first.
+    pub fn subroutines(&self) -> Vec<Subroutine> {
+        let mut inclusive = BTreeMap::new();
+        let mut exclusive = BTreeMap::new();
+
+        for (stack, count) in self.stacks() {
+            for entry in stack.iter().collect::<BTreeSet<&u16>>() {
+                *inclusive.entry(*entry).or_insert(0) += count;
+            }
+
+            *exclusive.entry(stack[stack.len() - 1]).or_insert(0) += count;
+        }
+
+        let mut subroutines: Vec<Subroutine> = inclusive
+            .iter()
+            .map(|(entry, inclusive)| Subroutine {
+                entry: *entry,
+                calls: self.calls.get(entry).copied().unwrap_or(0),
+                inclusive: *inclusive,
+                exclusive: exclusive.get(entry).copied().unwrap_or(0),
+            })
+            .collect();
+
+        subroutines.sort_by(|a, b| b.inclusive.cmp(&a.inclusive).then(a.entry.cmp(&b.entry)));
+        subroutines
+    }
+
+    /// Instructions executed by each stack of subroutine entry points, outermost first.
+    pub fn stacks(&self) -> Vec<(Vec<u16>, u64)> {
+        let mut stacks = self.stacks.clone();
+
+        if self.pending > 0 {
+            *stacks.entry(self.stack.clone()).or_insert(0) += self.pending;
+        }
+
+        let mut stacks: Vec<(Vec<u16>, u64)> = stacks.into_iter().collect();
+        stacks.sort();
+        stacks
+    }
+
+    fn flush(&mut self) {
+        if self.pending > 0 {
+            *self.stacks.entry(self.stack.clone()).or_insert(0) += self.pending;
+            self.pending = 0;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records the addresses and operations in turn.
+    fn profile(steps: &[(u16, Ops)]) -> Profile {
+        let mut profile = Profile::new(0x200);
+
+        for (pc, op) in steps {
+            profile.record(*pc, op);
+        }
+
+        profile
+    }
+
+    #[test]
+    fn counts_addresses_and_categories() {
+        let profile = profile(&[
+            (0x200, Ops::LD(0, 1)),
+            (0x202, Ops::ADD(0, 1)),
+            (0x204, Ops::JP(0x202)),
+            (0x202, Ops::ADD(0, 1)),
+        ]);
+
+        assert_eq!(profile.instructions, 4);
+        assert_eq!(
+            profile.hot_addresses(),
+            vec![
+                (0x202, 2, Ops::ADD(0, 1)),
+                (0x200, 1, Ops::LD(0, 1)),
+                (0x204, 1, Ops::JP(0x202)),
+            ]
+        );
+        assert_eq!(
+            profile.categories(),
+            vec![(Category::Arithmetic, 3), (Category::Flow, 1)]
+        );
+    }
+
+    #[test]
+    fn attributes_instructions_to_subroutines_through_calls() {
+        let profile = profile(&[
+            (0x200, Ops::CALL(0x300)),
+            (0x300, Ops::CALL(0x400)),
+            (0x400, Ops::LD(0, 1)),
+            (0x402, Ops::RET),
+            (0x302, Ops::RET),
+            (0x202, Ops::CALL(0x400)),
+            (0x400, Ops::LD(0, 1)),
+            (0x402, Ops::RET),
+            (0x204, Ops::JP(0x204)),
+        ]);
+
+        assert_eq!(
+            profile.stacks(),
+            vec![
+                (vec![0x200], 3),
+                (vec![0x200, 0x300], 2),
+                (vec![0x200, 0x300, 0x400], 2),
+                (vec![0x200, 0x400], 2),
+            ]
+        );
+        assert_eq!(
+            profile.subroutines(),
+            vec![
+                Subroutine {
+                    entry: 0x200,
+                    calls: 0,
+                    inclusive: 9,
+                    exclusive: 3
+                },
+                Subroutine {
+                    entry: 0x300,
+                    calls: 1,
+                    inclusive: 4,
+                    exclusive: 2
+                },
+                Subroutine {
+                    entry: 0x400,
+                    calls: 2,
+                    inclusive: 4,
+                    exclusive: 4
+                },
+            ]
+        );
+    }
+}
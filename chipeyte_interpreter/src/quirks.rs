@@ -0,0 +1,127 @@
+use crate::errors::ChipeyteError;
+use std::{fmt, str::FromStr};
+
+/// Names of the built-in quirk profiles, one per variant.
+pub const PROFILE_NAMES: [&str; 3] = ["chip8", "schip", "xochip"];
+
+/// Behaviours that differ between Chip-8 interpreters and that programs written for one of them
+/// may depend on.
+///
+/// The default leaves every quirk off, which is how Chipeyte has always behaved.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Quirks {
+    /// `8xy1`, `8xy2` and `8xy3` reset `VF` to 0.
+    pub vf_reset: bool,
+    /// `Fx55` and `Fx65` leave `I` pointing just past the last register stored or loaded.
+    pub memory_increment: bool,
+    /// `8xy6` and `8xyE` shift `Vy` into `Vx` instead of shifting `Vx` in place.
+    pub shift_vy: bool,
+    /// `Bnnn` jumps to `nnn` plus `Vx`, where `x` is the highest nibble of `nnn`, instead of `V0`.
+    pub jump_vx: bool,
+    /// Sprites are clipped at the edges of the display instead of wrapping around.
+    pub clip: bool,
+    /// `Dxyn` waits for the next vertical blank, so at most one sprite is drawn per frame.
+    pub display_wait: bool,
+}
+
+impl Quirks {
+    /// Looks up the quirks of a variant by its name.
+    pub fn profile(name: &str) -> Option<Quirks> {
+        name.parse::<Variant>().ok().map(|variant| variant.quirks())
+    }
+}
+
+/// The Chip-8 dialects, each a superset of the one before.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Variant {
+    /// The original interpreter of the COSMAC VIP.
+    Chip8,
+    /// SUPER-CHIP 1.1 on the HP 48.
+    Schip,
+    /// Octo's XO-CHIP.
+    Xochip,
+}
+
+impl Variant {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Variant::Chip8 => "chip8",
+            Variant::Schip => "schip",
+            Variant::Xochip => "xochip",
+        }
+    }
+
+    /// The quirks of the variant's reference interpreter.
+    pub fn quirks(&self) -> Quirks {
+        match self {
+            Variant::Chip8 => Quirks {
+                vf_reset: true,
+                memory_increment: true,
+                shift_vy: true,
+                jump_vx: false,
+                clip: true,
+                display_wait: true,
+            },
+            Variant::Schip => Quirks {
+                vf_reset: false,
+                memory_increment: false,
+                shift_vy: false,
+                jump_vx: true,
+                clip: true,
+                display_wait: false,
+            },
+            Variant::Xochip => Quirks {
+                vf_reset: false,
+                memory_increment: true,
+                shift_vy: true,
+                jump_vx: false,
+                clip: false,
+                display_wait: false,
+            },
+        }
+    }
+}
+
+impl fmt::Display for Variant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Variant {
+    type Err = ChipeyteError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "chip8" | "chip-8" => Ok(Variant::Chip8),
+            "schip" | "superchip" => Ok(Variant::Schip),
+            "xochip" | "xo-chip" => Ok(Variant::Xochip),
+            _ => Err(ChipeyteError::BadVariant(name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_profile_name_has_a_profile() {
+        for name in PROFILE_NAMES.iter() {
+            assert!(Quirks::profile(name).is_some(), "{}", name);
+        }
+
+        assert_eq!(Quirks::profile("chip48"), None);
+    }
+
+    #[test]
+    fn variants_parse_from_their_common_names() {
+        assert_eq!("CHIP-8".parse::<Variant>().unwrap(), Variant::Chip8);
+        assert_eq!("superchip".parse::<Variant>().unwrap(), Variant::Schip);
+        assert_eq!(
+            Variant::Xochip.to_string().parse::<Variant>().unwrap(),
+            Variant::Xochip
+        );
+        assert!("chip48".parse::<Variant>().is_err());
+    }
+}
@@ -0,0 +1,400 @@
+use crate::errors::ChipeyteError;
+use crate::rng::Rng;
+use std::f32::consts::PI;
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/// Shape of the beep.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Waveform {
+    Square,
+    Triangle,
+    Sine,
+    Sawtooth,
+    /// Random levels held for half a period, giving a pitched hiss.
+    Noise,
+}
+
+impl Waveform {
+    /// Level of the waveform at `phase`, from 0 to 1 over one period, between -1 and 1.
+    fn level(&self, phase: f32) -> f32 {
+        match self {
+            Waveform::Square | Waveform::Noise => {
+                if phase < 0.5 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Waveform::Triangle => 1.0 - 4.0 * (phase - 0.5).abs(),
+            Waveform::Sine => (2.0 * PI * phase).sin(),
+            Waveform::Sawtooth => 2.0 * phase - 1.0,
+        }
+    }
+}
+
+impl FromStr for Waveform {
+    type Err = ChipeyteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "square" => Ok(Waveform::Square),
+            "triangle" => Ok(Waveform::Triangle),
+            "sine" => Ok(Waveform::Sine),
+            "sawtooth" | "saw" => Ok(Waveform::Sawtooth),
+            "noise" => Ok(Waveform::Noise),
+            _ => Err(ChipeyteError::BadWaveform(s.to_string())),
+        }
+    }
+}
+
+/// Frames of emulated time per second.
+pub const FRAME_RATE: u32 = 60;
+
+/// An XO-CHIP audio pattern: 128 one-bit samples played as a loop at a rate set by `pitch`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct AudioPattern {
+    pub bits: [u8; 16],
+    pub pitch: u8,
+}
+
+impl AudioPattern {
+    /// Playback rate in bits per second, 4000 at the default pitch of 64.
+    pub fn rate(&self) -> f32 {
+        4000.0 * 2f32.powf((self.pitch as f32 - 64.0) / 48.0)
+    }
+
+    fn bit(&self, index: usize) -> bool {
+        self.bits[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+}
+
+/// What the speaker plays during one emulated frame.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct AudioFrame {
+    /// Whether the sound timer was running.
+    pub sounding: bool,
+    /// The pattern to play instead of the beep, if the program set one.
+    pub pattern: Option<AudioPattern>,
+}
+
+/// How the beep sounds.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Beep {
+    /// Pitch in Hz.
+    pub frequency: f32,
+    pub waveform: Waveform,
+    /// Peak amplitude, from 0 to 1.
+    pub volume: f32,
+    /// Seconds to fade in when the beep starts.
+    pub attack: f32,
+    /// Seconds to fade out when the beep stops. A short release avoids the click of cutting the
+    /// wave off mid-period.
+    pub release: f32,
+}
+
+impl Default for Beep {
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            waveform: Waveform::Square,
+            volume: 0.25,
+            attack: 0.002,
+            release: 0.01,
+        }
+    }
+}
+
+/// Generates the samples of a beep that is switched on and off, fading in and out according to
+/// its envelope.
+#[derive(Debug, Clone)]
+pub struct ToneGenerator {
+    beep: Beep,
+    sample_rate: u32,
+    phase: f32,
+    gain: f32,
+    on: bool,
+    noise: Rng,
+    noise_level: f32,
+    pattern: Option<AudioPattern>,
+    /// Frames rendered so far. Frames rarely hold a whole number of samples, so the length of each
+    /// is derived from the running total.
+    frames: u64,
+}
+
+impl ToneGenerator {
+    pub fn new(beep: Beep, sample_rate: u32) -> Self {
+        Self {
+            beep,
+            sample_rate: sample_rate.max(1),
+            phase: 0.0,
+            gain: 0.0,
+            on: false,
+            noise: Rng::new(1),
+            noise_level: 1.0,
+            pattern: None,
+            frames: 0,
+        }
+    }
+
+    /// Starts the attack or the release of the beep.
+    pub fn set_on(&mut self, on: bool) {
+        self.on = on;
+    }
+
+    /// Whether the beep is off and has faded out completely.
+    pub fn is_silent(&self) -> bool {
+        !self.on && self.gain == 0.0
+    }
+
+    pub fn next_sample(&mut self) -> f32 {
+        self.step_envelope();
+
+        if self.gain == 0.0 {
+            return 0.0;
+        }
+
+        if let Some(pattern) = self.pattern {
+            let level = if pattern.bit((self.phase * 128.0) as usize % 128) {
+                1.0
+            } else {
+                -1.0
+            };
+            self.phase = (self.phase + pattern.rate() / 128.0 / self.sample_rate as f32).fract();
+
+            return level * self.gain * self.beep.volume;
+        }
+
+        let level = match self.beep.waveform {
+            Waveform::Noise => self.noise_level,
+            waveform => waveform.level(self.phase),
+        };
+
+        let half = (self.phase * 2.0) as u32;
+        self.phase = (self.phase + self.beep.frequency / self.sample_rate as f32).fract();
+
+        if self.beep.waveform == Waveform::Noise && (self.phase * 2.0) as u32 != half {
+            self.noise_level = self.noise.next_byte() as f32 / 127.5 - 1.0;
+        }
+
+        level * self.gain * self.beep.volume
+    }
+
+    /// Appends the samples of one emulated frame to `out`, so a sound timer of `n` always plays
+    /// for exactly `n` frames worth of samples.
+    pub fn render_frame(&mut self, frame: &AudioFrame, out: &mut Vec<f32>) {
+        self.set_on(frame.sounding);
+        self.pattern = frame.pattern;
+
+        let rate = self.sample_rate as u64;
+        let frame_rate = FRAME_RATE as u64;
+        let samples = (self.frames + 1) * rate / frame_rate - self.frames * rate / frame_rate;
+        self.frames += 1;
+
+        for _ in 0..samples {
+            out.push(self.next_sample());
+        }
+    }
+
+    fn step_envelope(&mut self) {
+        let (target, seconds) = if self.on {
+            (1.0, self.beep.attack)
+        } else {
+            (0.0, self.beep.release)
+        };
+
+        let step = if seconds > 0.0 {
+            1.0 / (seconds * self.sample_rate as f32)
+        } else {
+            1.0
+        };
+
+        self.gain = if self.gain < target {
+            (self.gain + step).min(target)
+        } else {
+            (self.gain - step).max(target)
+        };
+
+        if self.gain == 0.0 {
+            // Restart the wave so every beep begins the same way
+            self.phase = 0.0;
+        }
+    }
+}
+
+/// Writes mono samples as a 16-bit PCM WAV file.
+pub fn write_wav<W: Write>(writer: &mut W, sample_rate: u32, samples: &[f32]) -> io::Result<()> {
+    let data_length = samples.len() as u32 * 2;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_length).to_le_bytes())?;
+    writer.write_all(b"WAVEfmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?; // PCM
+    writer.write_all(&1u16.to_le_bytes())?; // mono
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&(sample_rate * 2).to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_length.to_le_bytes())?;
+
+    for sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn beep(waveform: Waveform) -> Beep {
+        Beep {
+            frequency: 1000.0,
+            waveform,
+            volume: 1.0,
+            attack: 0.0,
+            release: 0.0,
+        }
+    }
+
+    fn samples(generator: &mut ToneGenerator, count: usize) -> Vec<f32> {
+        (0..count).map(|_| generator.next_sample()).collect()
+    }
+
+    #[test]
+    fn waveform_parses_names() {
+        assert_eq!("Sine".parse::<Waveform>().unwrap(), Waveform::Sine);
+        assert_eq!("saw".parse::<Waveform>().unwrap(), Waveform::Sawtooth);
+        assert!("pulse".parse::<Waveform>().is_err());
+    }
+
+    #[test]
+    fn generator_is_silent_until_switched_on() {
+        let mut generator = ToneGenerator::new(beep(Waveform::Square), 8000);
+
+        assert!(samples(&mut generator, 16).iter().all(|s| *s == 0.0));
+        assert!(generator.is_silent());
+    }
+
+    #[test]
+    fn square_wave_alternates_every_half_period() {
+        let mut generator = ToneGenerator::new(beep(Waveform::Square), 8000);
+        generator.set_on(true);
+
+        assert_eq!(
+            samples(&mut generator, 8),
+            vec![1.0, 1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0]
+        );
+    }
+
+    #[test]
+    fn waveforms_follow_their_shape() {
+        let mut triangle = ToneGenerator::new(beep(Waveform::Triangle), 4000);
+        let mut saw = ToneGenerator::new(beep(Waveform::Sawtooth), 4000);
+        let mut sine = ToneGenerator::new(beep(Waveform::Sine), 4000);
+        triangle.set_on(true);
+        saw.set_on(true);
+        sine.set_on(true);
+
+        assert_eq!(samples(&mut triangle, 4), vec![-1.0, 0.0, 1.0, 0.0]);
+        assert_eq!(samples(&mut saw, 4), vec![-1.0, -0.5, 0.0, 0.5]);
+        assert!((samples(&mut sine, 2)[1] - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn noise_stays_within_range_and_varies() {
+        let mut generator = ToneGenerator::new(beep(Waveform::Noise), 8000);
+        generator.set_on(true);
+
+        let noise = samples(&mut generator, 400);
+
+        assert!(noise.iter().all(|s| (-1.0..=1.0).contains(s)));
+        assert!(noise.windows(2).any(|w| w[0] != w[1]));
+    }
+
+    #[test]
+    fn envelope_ramps_volume_up_and_down() {
+        let mut generator = ToneGenerator::new(
+            Beep {
+                frequency: 0.0,
+                attack: 0.001,
+                release: 0.0005,
+                ..beep(Waveform::Square)
+            },
+            4000,
+        );
+
+        generator.set_on(true);
+        assert_eq!(samples(&mut generator, 5), vec![0.25, 0.5, 0.75, 1.0, 1.0]);
+
+        generator.set_on(false);
+        assert_eq!(samples(&mut generator, 3), vec![0.5, 0.0, 0.0]);
+        assert!(generator.is_silent());
+    }
+
+    #[test]
+    fn render_frame_renders_exactly_a_frame_of_samples() {
+        let mut generator = ToneGenerator::new(beep(Waveform::Square), 44_100);
+        let mut samples = Vec::new();
+        let on = AudioFrame {
+            sounding: true,
+            pattern: None,
+        };
+
+        for _ in 0..3 {
+            generator.render_frame(&on, &mut samples);
+        }
+        generator.render_frame(&AudioFrame::default(), &mut samples);
+
+        // 735 samples per frame at 44.1 kHz
+        assert_eq!(samples.len(), 4 * 735);
+        assert!(samples[..3 * 735].iter().all(|s| *s != 0.0));
+        assert!(samples[3 * 735..].iter().all(|s| *s == 0.0));
+    }
+
+    #[test]
+    fn render_frame_carries_fractions_of_samples_over() {
+        let mut generator = ToneGenerator::new(beep(Waveform::Square), 8000);
+        let mut samples = Vec::new();
+
+        for _ in 0..3 {
+            generator.render_frame(&AudioFrame::default(), &mut samples);
+        }
+
+        assert_eq!(samples.len(), 400);
+    }
+
+    #[test]
+    fn patterns_play_their_bits_at_the_pitch_rate() {
+        let mut generator = ToneGenerator::new(beep(Waveform::Sine), 4000);
+        let mut samples = Vec::new();
+        let mut bits = [0; 16];
+        bits[0] = 0b1010_0000;
+
+        generator.render_frame(
+            &AudioFrame {
+                sounding: true,
+                pattern: Some(AudioPattern { bits, pitch: 64 }),
+            },
+            &mut samples,
+        );
+
+        assert_eq!(&samples[..5], &[1.0, -1.0, 1.0, -1.0, -1.0]);
+    }
+
+    #[test]
+    fn write_wav_writes_a_pcm_header_and_samples() {
+        let mut wav = Vec::new();
+
+        write_wav(&mut wav, 8000, &[0.0, 1.0, -1.0]).unwrap();
+
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]), 6);
+        assert_eq!(&wav[44..], &[0, 0, 0xff, 0x7f, 0x01, 0x80]);
+    }
+}
@@ -0,0 +1,205 @@
+use crate::cpu::registers::Registers;
+use crate::cpu::INSTRUCTION_LENGTH;
+use crate::errors::ChipeyteError;
+use crate::operations::Ops;
+use std::{fmt, str::FromStr};
+
+/// Names of the timing models.
+pub const TIMING_NAMES: [&str; 2] = ["fixed", "vip"];
+
+/// Machine cycles of the VIP's 1802 per 60 Hz frame: 1.76 MHz at 8 clocks per machine cycle.
+const FRAME_CYCLES: u32 = 3668;
+/// Cycles taken by the interrupt routine repeating each of the 32 display rows on 4 of the 128
+/// scanlines, 14 cycles each.
+const DISPLAY_CYCLES: u32 = 128 * 14;
+/// Cycles taken by the rest of the interrupt routine, which decrements the timers.
+const INTERRUPT_CYCLES: u32 = 46;
+
+/// Cycles the interpreter loop takes to fetch and decode an instruction.
+const FETCH: u32 = 40;
+/// Extra cycles of a conditional that skips the next instruction.
+const SKIP: u32 = 4;
+/// Extra cycles of `Bnnn` and `Fx1E` when the address crosses into the next page.
+const PAGE_CROSSING: u32 = 4;
+/// Cycles of `Dxyn` before its first row and for each row of the sprite.
+const DRW_SETUP: u32 = 26;
+const DRW_ROW: u32 = 38;
+/// Extra cycles per row of a sprite not aligned to a display byte, which is shifted a bit at a
+/// time and spread over two bytes.
+const DRW_SHIFT: u32 = 8;
+const DRW_SPLIT: u32 = 20;
+
+/// How long instructions take, which decides how many run per frame.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Timing {
+    /// Every instruction takes the same time, set by the clock speed or instructions per frame.
+    Fixed,
+    /// Instructions take as many machine cycles as in the interpreter of the COSMAC VIP, and
+    /// `Dxyn` waits for the vertical blank interrupt before drawing.
+    CosmacVip,
+}
+
+impl Timing {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Timing::Fixed => "fixed",
+            Timing::CosmacVip => "vip",
+        }
+    }
+
+    /// Machine cycles left to the program per frame, 0 for `Timing::Fixed`.
+    pub fn cycles_per_frame(&self) -> u32 {
+        match self {
+            Timing::Fixed => 0,
+            Timing::CosmacVip => FRAME_CYCLES - DISPLAY_CYCLES - INTERRUPT_CYCLES,
+        }
+    }
+
+    /// Machine cycles taken by the operation, given the registers before and after running it.
+    pub(crate) fn cycles(&self, op: &Ops, before: &Registers, after: &Registers) -> u32 {
+        match self {
+            Timing::Fixed => 0,
+            Timing::CosmacVip => FETCH + vip_cycles(op, before, after),
+        }
+    }
+}
+
+/// Cycles of the VIP routine of the operation. Operations the VIP does not have take none.
+fn vip_cycles(op: &Ops, before: &Registers, after: &Registers) -> u32 {
+    let v = |x: u8| before.get_data_register_value(x).unwrap_or(0);
+    let skip = if after.pc > before.pc + INSTRUCTION_LENGTH {
+        SKIP
+    } else {
+        0
+    };
+    let page_crossing = |address: u16, offset: u8| {
+        if (address & 0xff) + offset as u16 > 0xff {
+            PAGE_CROSSING
+        } else {
+            0
+        }
+    };
+
+    match op {
+        Ops::SYS(_) | Ops::CALL(_) => 26,
+        // One store and a two instruction loop per display byte
+        Ops::CLS => 24 + 256 * 6,
+        Ops::RET => 10,
+        Ops::JP(_) | Ops::LDV(_, _) | Ops::LDI(_) => 12,
+        Ops::SE(_, _) | Ops::SNE(_, _) => 10 + skip,
+        Ops::SEV(_, _) | Ops::SNEV(_, _) | Ops::SKP(_) | Ops::SKNP(_) => 14 + skip,
+        Ops::LD(_, _) => 6,
+        Ops::ADD(_, _) | Ops::LDVDT(_) | Ops::LDK(_) | Ops::LDDT(_) | Ops::LDST(_) => 10,
+        Ops::OR(_, _)
+        | Ops::AND(_, _)
+        | Ops::XOR(_, _)
+        | Ops::ADDV(_, _)
+        | Ops::SUB(_, _)
+        | Ops::SHR(_, _)
+        | Ops::SUBN(_, _)
+        | Ops::SHL(_, _) => 44,
+        Ops::JPV0(address) => 22 + page_crossing(*address, before.v0),
+        Ops::RND(_, _) => 36,
+        Ops::DRW(x, _, n) => {
+            let shift = (v(*x) % 8) as u32;
+            let row = if shift == 0 {
+                DRW_ROW
+            } else {
+                DRW_ROW + DRW_SPLIT + shift * DRW_SHIFT
+            };
+
+            DRW_SETUP + *n as u32 * row
+        }
+        Ops::ADDI(x) => 16 + page_crossing(before.i, v(*x)),
+        Ops::LDF(_) => 16,
+        // Converts by repeated subtraction, so larger digits take longer
+        Ops::LDB(x) => {
+            let value = v(*x) as u32;
+
+            80 + 16 * (value / 100 + value / 10 % 10 + value % 10)
+        }
+        Ops::LDIV(x) | Ops::LDVI(x) => 14 + 14 * (*x as u32 + 1),
+        _ => 0,
+    }
+}
+
+impl fmt::Display for Timing {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Timing {
+    type Err = ChipeyteError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_lowercase().as_str() {
+            "fixed" => Ok(Timing::Fixed),
+            "vip" | "cosmac-vip" => Ok(Timing::CosmacVip),
+            _ => Err(ChipeyteError::BadTiming(name.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cycles(op: Ops, before: &Registers, skipped: bool) -> u32 {
+        let after = Registers {
+            pc: before.pc + if skipped { 4 } else { 2 },
+            ..before.clone()
+        };
+
+        Timing::CosmacVip.cycles(&op, before, &after)
+    }
+
+    #[test]
+    fn skips_and_page_crossings_take_longer() {
+        let registers = Registers {
+            v0: 0x10,
+            i: 0x3f8,
+            ..Registers::new(0x200)
+        };
+
+        assert_eq!(cycles(Ops::SE(0, 0x10), &registers, true), FETCH + 14);
+        assert_eq!(cycles(Ops::SE(0, 0x11), &registers, false), FETCH + 10);
+        assert_eq!(cycles(Ops::ADDI(0), &registers, false), FETCH + 20);
+        assert_eq!(cycles(Ops::JPV0(0x300), &registers, false), FETCH + 22);
+        assert_eq!(Timing::Fixed.cycles(&Ops::CLS, &registers, &registers), 0);
+    }
+
+    #[test]
+    fn sprites_cost_more_when_taller_or_unaligned() {
+        let aligned = Registers {
+            v0: 8,
+            ..Registers::new(0x200)
+        };
+        let shifted = Registers {
+            v0: 11,
+            ..aligned.clone()
+        };
+
+        assert_eq!(
+            cycles(Ops::DRW(0, 1, 5), &aligned, false),
+            FETCH + DRW_SETUP + 5 * DRW_ROW
+        );
+        assert_eq!(
+            cycles(Ops::DRW(0, 1, 5), &shifted, false),
+            FETCH + DRW_SETUP + 5 * (DRW_ROW + DRW_SPLIT + 3 * DRW_SHIFT)
+        );
+        assert!(
+            cycles(Ops::DRW(0, 1, 15), &aligned, false)
+                > cycles(Ops::DRW(0, 1, 5), &aligned, false)
+        );
+    }
+
+    #[test]
+    fn timings_parse_from_their_names() {
+        for name in TIMING_NAMES.iter() {
+            assert_eq!(name.parse::<Timing>().unwrap().name(), *name);
+        }
+
+        assert!("pal".parse::<Timing>().is_err());
+    }
+}
@@ -0,0 +1,378 @@
+use crate::cpu::registers::Registers;
+use crate::errors::ChipeyteError;
+use crate::memory::Memory;
+use crate::operations::Ops;
+use std::{fmt, io, str::FromStr};
+
+/// Names of the trace formats.
+pub const FORMAT_NAMES: [&str; 2] = ["text", "jsonl"];
+
+/// A register that an instruction can change. The program counter changes with every instruction
+/// and is left out.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub enum Register {
+    V(u8),
+    I,
+    SP,
+    DT,
+    ST,
+}
+
+impl Register {
+    /// Every register, in the order they are traced.
+    pub fn all() -> impl Iterator<Item = Register> {
+        (0..16)
+            .map(Register::V)
+            .chain(vec![Register::I, Register::SP, Register::DT, Register::ST])
+    }
+
+    fn value(&self, registers: &Registers) -> u16 {
+        match self {
+            Register::V(x) => registers.get_data_register_value(*x).unwrap_or(0) as u16,
+            Register::I => registers.i,
+            Register::SP => registers.sp as u16,
+            Register::DT => registers.dt as u16,
+            Register::ST => registers.st as u16,
+        }
+    }
+
+    /// Formats a value of the register as hex, with as many digits as the register holds.
+    pub fn format(&self, value: u16) -> String {
+        match self {
+            Register::I => format!("{:04x}", value),
+            _ => format!("{:02x}", value),
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::V(x) => write!(f, "V{:X}", x),
+            Register::I => write!(f, "I"),
+            Register::SP => write!(f, "SP"),
+            Register::DT => write!(f, "DT"),
+            Register::ST => write!(f, "ST"),
+        }
+    }
+}
+
+impl FromStr for Register {
+    type Err = ChipeyteError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Register::all()
+            .find(|register| register.to_string().eq_ignore_ascii_case(name))
+            .ok_or_else(|| ChipeyteError::BadRegister(name.to_string()))
+    }
+}
+
+/// What a single executed instruction did.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TraceEntry {
+    /// Number of instructions executed before this one.
+    pub cycle: u64,
+    pub pc: u16,
+    pub opcode: u16,
+    pub op: Ops,
+    /// Registers the instruction changed, with their new values.
+    pub registers: Vec<(Register, u16)>,
+    /// Bytes the instruction stored, by address.
+    pub writes: Vec<(u16, u8)>,
+}
+
+impl TraceEntry {
+    /// The entry as a single line of JSON, with every number in hex.
+    pub fn to_json(&self) -> String {
+        let registers = self
+            .registers
+            .iter()
+            .map(|(register, value)| format!("\"{}\":\"{}\"", register, register.format(*value)))
+            .collect::<Vec<String>>()
+            .join(",");
+        let writes = self
+            .writes
+            .iter()
+            .map(|(address, value)| format!("\"{:04x}\":\"{:02x}\"", address, value))
+            .collect::<Vec<String>>()
+            .join(",");
+
+        format!(
+            "{{\"cycle\":{},\"pc\":\"{:04x}\",\"opcode\":\"{:04x}\",\"op\":\"{}\",\"registers\":{{{}}},\"memory\":{{{}}}}}",
+            self.cycle, self.pc, self.opcode, self.op, registers, writes
+        )
+    }
+}
+
+/// One line per instruction: cycle, address, opcode, mnemonic and what changed, such as
+/// `      12 0204 7001 ADD V0, 0x01         V0=05 [0300]=12`.
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changes = self
+            .registers
+            .iter()
+            .map(|(register, value)| format!(" {}={}", register, register.format(*value)))
+            .chain(
+                self.writes
+                    .iter()
+                    .map(|(address, value)| format!(" [{:04x}]={:02x}", address, value)),
+            )
+            .collect::<String>();
+        let op = match changes.len() {
+            0 => self.op.to_string(),
+            _ => format!("{:<20}", self.op.to_string()),
+        };
+
+        write!(
+            f,
+            "{:>8} {:04x} {:04x} {}{}",
+            self.cycle, self.pc, self.opcode, op, changes
+        )
+    }
+}
+
+/// The registers that differ between `before` and `after`, with their values after.
+pub(crate) fn changed_registers(before: &Registers, after: &Registers) -> Vec<(Register, u16)> {
+    Register::all()
+        .filter(|register| register.value(before) != register.value(after))
+        .map(|register| (register, register.value(after)))
+        .collect()
+}
+
+/// The bytes in the range `from..=to`, by address.
+pub(crate) fn stored_bytes(range: Option<(u16, u16)>, memory: &Memory) -> Vec<(u16, u8)> {
+    match range {
+        Some((from, to)) => (from..=to)
+            .map(|address| (address, memory.get(address.into())))
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+/// When tracing starts or stops.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Trigger {
+    /// On reaching an instruction between the two addresses, inclusive.
+    Pc(u16, u16),
+    /// On reaching the given number of executed instructions.
+    Cycle(u64),
+}
+
+impl Trigger {
+    fn fires(&self, cycle: u64, pc: u16) -> bool {
+        match self {
+            Trigger::Pc(from, to) => *from <= pc && pc <= *to,
+            Trigger::Cycle(n) => cycle >= *n,
+        }
+    }
+}
+
+/// Parses `pc=ADDR`, `pc=ADDR-ADDR` with hex addresses, or `cycle=N`.
+impl FromStr for Trigger {
+    type Err = ChipeyteError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let bad = || ChipeyteError::BadTrigger(input.to_string());
+        let address =
+            |hex: &str| u16::from_str_radix(hex.trim_start_matches("0x"), 16).map_err(|_| bad());
+
+        match input.split_once('=') {
+            Some(("pc", range)) => match range.split_once('-') {
+                Some((from, to)) => Ok(Trigger::Pc(address(from)?, address(to)?)),
+                None => address(range).map(|pc| Trigger::Pc(pc, pc)),
+            },
+            Some(("cycle", n)) => n.parse().map(Trigger::Cycle).map_err(|_| bad()),
+            _ => Err(bad()),
+        }
+    }
+}
+
+/// The part of a run to trace. The instructions that fire the triggers are both traced; without a
+/// start trigger tracing starts right away and without a stop trigger it never stops.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct TraceWindow {
+    pub start: Option<Trigger>,
+    pub stop: Option<Trigger>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum TraceState {
+    Waiting,
+    Recording,
+    Stopped,
+}
+
+/// Collects the entries of the instructions within a trace window.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Tracer {
+    window: TraceWindow,
+    state: TraceState,
+    entries: Vec<TraceEntry>,
+}
+
+impl Tracer {
+    pub fn new(window: TraceWindow) -> Tracer {
+        Tracer {
+            window,
+            state: TraceState::Waiting,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Whether the instruction about to be executed is traced.
+    pub fn is_recording(&mut self, cycle: u64, pc: u16) -> bool {
+        if self.state == TraceState::Waiting
+            && self.window.start.is_none_or(|start| start.fires(cycle, pc))
+        {
+            self.state = TraceState::Recording;
+        }
+
+        self.state == TraceState::Recording
+    }
+
+    pub fn record(&mut self, entry: TraceEntry) {
+        if let Some(stop) = self.window.stop {
+            if stop.fires(entry.cycle, entry.pc) {
+                self.state = TraceState::Stopped;
+            }
+        }
+
+        self.entries.push(entry);
+    }
+
+    /// Takes the entries recorded so far.
+    pub fn drain(&mut self) -> Vec<TraceEntry> {
+        std::mem::take(&mut self.entries)
+    }
+}
+
+/// How a trace is written.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TraceFormat {
+    /// One aligned line per instruction, for reading.
+    Text,
+    /// One JSON object per line, for tools.
+    JsonLines,
+}
+
+impl FromStr for TraceFormat {
+    type Err = ChipeyteError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "text" => Ok(TraceFormat::Text),
+            "jsonl" => Ok(TraceFormat::JsonLines),
+            _ => Err(ChipeyteError::BadTraceFormat(name.to_string())),
+        }
+    }
+}
+
+/// Writes the trace of a run as it goes.
+pub struct TraceWriter {
+    out: Box<dyn io::Write>,
+    format: TraceFormat,
+    pub window: TraceWindow,
+}
+
+impl TraceWriter {
+    pub fn new(out: Box<dyn io::Write>, format: TraceFormat, window: TraceWindow) -> Self {
+        TraceWriter {
+            out,
+            format,
+            window,
+        }
+    }
+
+    pub fn write(&mut self, entries: &[TraceEntry]) -> io::Result<()> {
+        for entry in entries {
+            match self.format {
+                TraceFormat::Text => writeln!(self.out, "{}", entry)?,
+                TraceFormat::JsonLines => writeln!(self.out, "{}", entry.to_json())?,
+            }
+        }
+
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry() -> TraceEntry {
+        TraceEntry {
+            cycle: 12,
+            pc: 0x204,
+            opcode: 0xf155,
+            op: Ops::LDIV(1),
+            registers: vec![(Register::V(0xf), 1), (Register::I, 0x302)],
+            writes: vec![(0x300, 0x12), (0x301, 0x34)],
+        }
+    }
+
+    #[test]
+    fn formats_entries_as_text_and_json() {
+        assert_eq!(
+            entry().to_string(),
+            "      12 0204 f155 LD [I], V1           VF=01 I=0302 [0300]=12 [0301]=34"
+        );
+        assert_eq!(
+            entry().to_json(),
+            "{\"cycle\":12,\"pc\":\"0204\",\"opcode\":\"f155\",\"op\":\"LD [I], V1\",\
+             \"registers\":{\"VF\":\"01\",\"I\":\"0302\"},\"memory\":{\"0300\":\"12\",\"0301\":\"34\"}}"
+        );
+    }
+
+    #[test]
+    fn parses_triggers_and_registers() {
+        assert_eq!(
+            "pc=300".parse::<Trigger>().unwrap(),
+            Trigger::Pc(0x300, 0x300)
+        );
+        assert_eq!(
+            "pc=0x300-3ff".parse::<Trigger>().unwrap(),
+            Trigger::Pc(0x300, 0x3ff)
+        );
+        assert_eq!(
+            "cycle=1000".parse::<Trigger>().unwrap(),
+            Trigger::Cycle(1000)
+        );
+        assert!("pc=xyz".parse::<Trigger>().is_err());
+        assert!("frame=3".parse::<Trigger>().is_err());
+
+        assert_eq!("va".parse::<Register>().unwrap(), Register::V(0xa));
+        assert_eq!("SP".parse::<Register>().unwrap(), Register::SP);
+        assert!("PC".parse::<Register>().is_err());
+    }
+
+    #[test]
+    fn records_from_the_start_trigger_through_the_stop_trigger() {
+        let mut tracer = Tracer::new(TraceWindow {
+            start: Some(Trigger::Pc(0x204, 0x204)),
+            stop: Some(Trigger::Cycle(3)),
+        });
+
+        let recorded: Vec<u64> = [0x200, 0x202, 0x204, 0x206, 0x208]
+            .iter()
+            .enumerate()
+            .filter_map(|(cycle, pc)| {
+                let cycle = cycle as u64;
+
+                if !tracer.is_recording(cycle, *pc) {
+                    return None;
+                }
+
+                tracer.record(TraceEntry {
+                    cycle,
+                    pc: *pc,
+                    ..entry()
+                });
+                Some(cycle)
+            })
+            .collect();
+
+        assert_eq!(recorded, vec![2, 3]);
+        assert_eq!(tracer.drain().len(), 2);
+        assert!(tracer.drain().is_empty());
+    }
+}
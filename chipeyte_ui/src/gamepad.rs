@@ -0,0 +1,98 @@
+use crate::keymap::parse_key;
+use std::collections::HashMap;
+
+/// Maps gamepad buttons to Chip-8 keys.
+///
+/// Buttons are identified by their SDL GameController names: `dpup`, `dpdown`, `dpleft`,
+/// `dpright`, `a`, `b`, `x`, `y`, `back`, `guide`, `start`, `leftstick`, `rightstick`,
+/// `leftshoulder` and `rightshoulder`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GamepadBindings {
+    buttons: HashMap<String, u8>,
+}
+
+impl GamepadBindings {
+    /// Bindings without any buttons.
+    pub fn empty() -> Self {
+        Self {
+            buttons: HashMap::new(),
+        }
+    }
+
+    /// Binds `button` to the Chip-8 key `key`, replacing any previous binding of `button`.
+    pub fn bind(&mut self, button: &str, key: u8) {
+        self.buttons.insert(button.to_lowercase(), key & 0xf);
+    }
+
+    /// Adds bindings given as button names and hex digits, e.g. `("a", "5")`.
+    pub fn bind_all<'a, I>(&mut self, bindings: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = (&'a String, &'a String)>,
+    {
+        for (button, key) in bindings {
+            self.bind(button, parse_key(button, key)?);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the Chip-8 key bound to `button`.
+    pub fn key(&self, button: &str) -> Option<u8> {
+        self.buttons.get(&button.to_lowercase()).copied()
+    }
+}
+
+impl Default for GamepadBindings {
+    /// The D-pad moves with 2/8/4/6, as most games do, and the face buttons and start press the
+    /// common action keys.
+    fn default() -> Self {
+        let mut bindings = Self::empty();
+
+        for (button, key) in [
+            ("dpup", 0x2),
+            ("dpdown", 0x8),
+            ("dpleft", 0x4),
+            ("dpright", 0x6),
+            ("a", 0x5),
+            ("b", 0x0),
+            ("x", 0x7),
+            ("y", 0x9),
+            ("start", 0xf),
+            ("back", 0xe),
+        ]
+        .iter()
+        {
+            bindings.bind(button, *key);
+        }
+
+        bindings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_bindings_move_with_the_dpad() {
+        let bindings = GamepadBindings::default();
+
+        assert_eq!(bindings.key("dpup"), Some(0x2));
+        assert_eq!(bindings.key("DPLEFT"), Some(0x4));
+        assert_eq!(bindings.key("guide"), None);
+    }
+
+    #[test]
+    fn bind_all_overrides_default_bindings() {
+        let mut bindings = GamepadBindings::default();
+        let overrides = [("a".to_string(), "c".to_string())]
+            .iter()
+            .cloned()
+            .collect::<HashMap<String, String>>();
+
+        bindings.bind_all(&overrides).unwrap();
+
+        assert_eq!(bindings.key("a"), Some(0xc));
+        assert_eq!(bindings.key("dpup"), Some(0x2));
+    }
+}
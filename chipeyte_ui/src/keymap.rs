@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+/// Names of the built-in keymaps.
+pub const PRESET_NAMES: [&str; 3] = ["classic", "modern", "numpad"];
+
+/// Maps host keys to the 16 Chip-8 keys.
+///
+/// Host keys are identified by name, as reported by the front end (e.g. `Q`, `1` or `Keypad 7`),
+/// and compared case-insensitively. A Chip-8 key may be bound to several host keys.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Keymap {
+    pub name: String,
+    keys: HashMap<String, u8>,
+}
+
+impl Keymap {
+    /// Looks up a built-in keymap by name.
+    ///
+    /// Each preset lays the keys out like the original hex keypad:
+    ///
+    ///   ,---------------.
+    ///   | 1 | 2 | 3 | C |
+    ///   |---|---|---|---|
+    ///   | 4 | 5 | 6 | D |
+    ///   |---|---|---|---|
+    ///   | 7 | 8 | 9 | E |
+    ///   |---|---|---|---|
+    ///   | A | 0 | B | F |
+    ///   `---------------´
+    ///
+    /// `classic` uses 1234/QWER/ASDF/ZXCV, `modern` 6789/YUIO/HJKL/NM,. and `numpad` the numeric
+    /// keypad with `/`, `*`, `-` and `+` as the rightmost column.
+    pub fn preset(name: &str) -> Option<Keymap> {
+        let host_keys: [&str; 16] = match name {
+            "classic" => [
+                "1", "2", "3", "4", "Q", "W", "E", "R", "A", "S", "D", "F", "Z", "X", "C", "V",
+            ],
+            "modern" => [
+                "6", "7", "8", "9", "Y", "U", "I", "O", "H", "J", "K", "L", "N", "M", ",", ".",
+            ],
+            "numpad" => [
+                "Keypad 7",
+                "Keypad 8",
+                "Keypad 9",
+                "Keypad /",
+                "Keypad 4",
+                "Keypad 5",
+                "Keypad 6",
+                "Keypad *",
+                "Keypad 1",
+                "Keypad 2",
+                "Keypad 3",
+                "Keypad -",
+                "Keypad 0",
+                "Keypad .",
+                "Keypad Enter",
+                "Keypad +",
+            ],
+            _ => return None,
+        };
+
+        let mut keymap = Keymap::empty(name);
+
+        for (host_key, key) in host_keys.iter().zip(KEYPAD_LAYOUT.iter()) {
+            keymap.bind(host_key, *key);
+        }
+
+        Some(keymap)
+    }
+
+    /// A keymap without any bindings.
+    pub fn empty(name: &str) -> Keymap {
+        Keymap {
+            name: name.to_string(),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Binds `host_key` to the Chip-8 key `key`, replacing any previous binding of `host_key`.
+    pub fn bind(&mut self, host_key: &str, key: u8) {
+        self.keys.insert(host_key.to_lowercase(), key & 0xf);
+    }
+
+    /// Adds bindings given as host key names and hex digits, e.g. `("Space", "5")`.
+    pub fn bind_all<'a, I>(&mut self, bindings: I) -> Result<(), String>
+    where
+        I: IntoIterator<Item = (&'a String, &'a String)>,
+    {
+        for (host_key, key) in bindings {
+            self.bind(host_key, parse_key(host_key, key)?);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the Chip-8 key bound to `host_key`.
+    pub fn key(&self, host_key: &str) -> Option<u8> {
+        self.keys.get(&host_key.to_lowercase()).copied()
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Keymap::preset("modern").unwrap()
+    }
+}
+
+/// Parses the Chip-8 key, a single hex digit, that `input` is bound to.
+pub fn parse_key(input: &str, key: &str) -> Result<u8, String> {
+    match u8::from_str_radix(key.trim(), 16) {
+        Ok(key) if key <= 0xf => Ok(key),
+        _ => Err(format!(
+            "Cannot bind '{}' to '{}', expected a Chip-8 key from 0 to F",
+            input, key
+        )),
+    }
+}
+
+/// Chip-8 keys in the order they appear on the hex keypad, row by row.
+const KEYPAD_LAYOUT: [u8; 16] = [
+    0x1, 0x2, 0x3, 0xc, 0x4, 0x5, 0x6, 0xd, 0x7, 0x8, 0x9, 0xe, 0xa, 0x0, 0xb, 0xf,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bindings(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(host, key)| (host.to_string(), key.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn every_preset_binds_all_sixteen_keys() {
+        for name in PRESET_NAMES.iter() {
+            let keymap = Keymap::preset(name).unwrap();
+            let mut keys = keymap.keys.values().copied().collect::<Vec<u8>>();
+            keys.sort_unstable();
+
+            assert_eq!(keys, (0..16).collect::<Vec<u8>>(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn classic_preset_follows_the_hex_keypad() {
+        let keymap = Keymap::preset("classic").unwrap();
+
+        assert_eq!(keymap.key("1"), Some(0x1));
+        assert_eq!(keymap.key("R"), Some(0xd));
+        assert_eq!(keymap.key("x"), Some(0x0));
+        assert_eq!(keymap.key("V"), Some(0xf));
+        assert_eq!(keymap.key("Space"), None);
+    }
+
+    #[test]
+    fn bind_all_overrides_and_adds_bindings() {
+        let mut keymap = Keymap::preset("classic").unwrap();
+        let bindings = bindings(&[("Q", "a"), ("Space", "5")]);
+
+        keymap.bind_all(&bindings).unwrap();
+
+        assert_eq!(keymap.key("Q"), Some(0xa));
+        assert_eq!(keymap.key("Space"), Some(0x5));
+        assert_eq!(keymap.key("W"), Some(0x5));
+    }
+
+    #[test]
+    fn bind_all_rejects_keys_outside_the_keypad() {
+        let mut keymap = Keymap::empty("custom");
+
+        assert!(keymap.bind_all(&bindings(&[("Q", "10")])).is_err());
+    }
+}
@@ -0,0 +1,97 @@
+/// Where the display is drawn inside a window: an integer-scaled, centered area with letterbox
+/// bars filling the rest.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Viewport {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Size of a single Chip-8 pixel in window pixels.
+    pub pixel_size: u32,
+}
+
+impl Viewport {
+    /// Fits a `display_width` x `display_height` display into a window using the largest integer
+    /// scale that fits, never going below a scale of 1.
+    pub fn fit(
+        window_width: u32,
+        window_height: u32,
+        display_width: usize,
+        display_height: usize,
+    ) -> Viewport {
+        let (display_width, display_height) = (display_width as u32, display_height as u32);
+
+        let pixel_size = (window_width / display_width)
+            .min(window_height / display_height)
+            .max(1);
+
+        let width = display_width * pixel_size;
+        let height = display_height * pixel_size;
+
+        Viewport {
+            x: (window_width as i32 - width as i32) / 2,
+            y: (window_height as i32 - height as i32) / 2,
+            width,
+            height,
+            pixel_size,
+        }
+    }
+
+    /// Window position of the top left corner of the display pixel at (`x`, `y`).
+    pub fn position(&self, x: usize, y: usize) -> (i32, i32) {
+        (
+            self.x + (x as u32 * self.pixel_size) as i32,
+            self.y + (y as u32 * self.pixel_size) as i32,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_uses_the_whole_window_when_it_matches_the_aspect_ratio() {
+        assert_eq!(
+            Viewport::fit(640, 320, 64, 32),
+            Viewport {
+                x: 0,
+                y: 0,
+                width: 640,
+                height: 320,
+                pixel_size: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn fit_letterboxes_and_only_scales_by_whole_numbers() {
+        let viewport = Viewport::fit(1000, 700, 64, 32);
+
+        assert_eq!(viewport.pixel_size, 15);
+        assert_eq!((viewport.width, viewport.height), (960, 480));
+        assert_eq!((viewport.x, viewport.y), (20, 110));
+    }
+
+    #[test]
+    fn fit_relayouts_for_other_resolutions() {
+        assert_eq!(Viewport::fit(640, 320, 128, 64).pixel_size, 5);
+        assert_eq!(Viewport::fit(640, 320, 64, 64).pixel_size, 5);
+        assert_eq!(Viewport::fit(640, 320, 64, 64).x, 160);
+    }
+
+    #[test]
+    fn fit_never_scales_below_one() {
+        let viewport = Viewport::fit(32, 16, 64, 32);
+
+        assert_eq!(viewport.pixel_size, 1);
+        assert_eq!((viewport.x, viewport.y), (-16, -8));
+    }
+
+    #[test]
+    fn position_offsets_by_the_letterbox() {
+        let viewport = Viewport::fit(1000, 700, 64, 32);
+
+        assert_eq!(viewport.position(1, 2), (35, 140));
+    }
+}
@@ -1,4 +1,38 @@
+pub mod gamepad;
+pub mod keymap;
+pub mod layout;
 pub mod mock;
+pub mod palette;
 
 #[cfg(feature = "sdl2-ui")]
 pub mod sdl2;
+
+use chipeyte_interpreter::sound::Beep;
+use gamepad::GamepadBindings;
+use keymap::Keymap;
+use palette::Palette;
+
+/// Presentation settings shared by the front ends.
+pub struct UIConfig {
+    /// Name of the running program, shown in the window title.
+    pub title: Option<String>,
+    pub palette: Palette,
+    /// Initial size of a Chip-8 pixel in window pixels.
+    pub scale: u32,
+    pub keymap: Keymap,
+    pub gamepad: GamepadBindings,
+    pub beep: Beep,
+}
+
+impl Default for UIConfig {
+    fn default() -> Self {
+        Self {
+            title: None,
+            palette: Palette::default(),
+            scale: 10,
+            keymap: Keymap::default(),
+            gamepad: GamepadBindings::default(),
+            beep: Beep::default(),
+        }
+    }
+}
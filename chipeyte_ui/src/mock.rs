@@ -1,56 +1,112 @@
-use chipeyte_interpreter::interface;
-use std::collections::HashSet;
+use crate::UIConfig;
+use chipeyte_interpreter::{
+    interface::{self, UserAction},
+    postprocess::Frame,
+    sound::AudioFrame,
+};
+use std::collections::VecDeque;
 
 pub struct MockUI {
     pub screen: Screen,
+    pub input: ScriptedInput,
     pub speaker: Speaker,
     pub controller: interface::Controller,
 }
 
 impl MockUI {
-    pub fn init(_fg_color: interface::Color, _bg_color: interface::Color) -> Self {
-        Self {
-            screen: Screen {
-                pixels: HashSet::new(),
-            },
-            speaker: Speaker {},
+    pub fn init(_config: UIConfig) -> Result<Self, String> {
+        Ok(Self {
+            screen: Screen { last_frame: None },
+            input: ScriptedInput::new(Vec::new()),
+            speaker: Speaker { sounding_frames: 0 },
             controller: interface::Controller::new(),
-        }
+        })
     }
 }
 
 pub struct Screen {
-    pixels: HashSet<(u8, u8)>,
+    /// The most recently presented frame, for inspection by headless runs.
+    pub last_frame: Option<Frame>,
+}
+pub struct Speaker {
+    /// Number of frames the sound timer was running, for inspection by headless runs.
+    pub sounding_frames: u64,
 }
-pub struct Speaker {}
 
 impl interface::Drawable for Screen {
-    fn clear(&mut self) {
-        self.pixels.clear();
-    }
-    fn add_pixel(&mut self, x: u8, y: u8) {
-        self.pixels.insert((x, y));
+    fn render(&mut self, frame: &Frame) {
+        self.last_frame = Some(frame.clone());
     }
-    fn remove_pixel(&mut self, x: u8, y: u8) {
-        self.pixels.remove(&(x, y));
-    }
-    fn has_pixel(&self, x: u8, y: u8) -> bool {
-        self.pixels.contains(&(x, y))
-    }
-    fn render(&mut self) {}
+}
 
-    fn poll_events(&mut self) -> Option<interface::UserAction> {
-        None
+/// Input played back from a script of actions, each tagged with the frame it happens on.
+///
+/// The input is polled once per frame, so the n:th poll returns the actions scripted for frame n.
+pub struct ScriptedInput {
+    script: VecDeque<(u32, UserAction)>,
+    frame: u32,
+}
+
+impl ScriptedInput {
+    pub fn new(mut script: Vec<(u32, UserAction)>) -> Self {
+        script.sort_by_key(|(frame, _)| *frame);
+
+        Self {
+            script: script.into(),
+            frame: 0,
+        }
     }
-    fn get_pixels(&self) -> std::collections::HashSet<(u8, u8)> {
-        self.pixels.clone()
+}
+
+impl interface::InputSource for ScriptedInput {
+    fn poll_events(&mut self) -> Vec<UserAction> {
+        let mut events = Vec::new();
+
+        while let Some((frame, _)) = self.script.front() {
+            if *frame > self.frame {
+                break;
+            }
+
+            let (_, action) = self.script.pop_front().unwrap();
+            events.push(action);
+        }
+
+        self.frame += 1;
+        events
     }
 }
 
 impl interface::Audible for Speaker {
-    fn play_sound(&self) {}
-    fn stop_sound(&self) {}
-    fn is_playing(&self) -> bool {
-        false
+    fn play_frame(&mut self, frame: &AudioFrame) {
+        if frame.sounding {
+            self.sounding_frames += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chipeyte_interpreter::interface::InputSource;
+
+    #[test]
+    fn scripted_input_plays_actions_back_on_their_frames() {
+        let mut input = ScriptedInput::new(vec![
+            (2, UserAction::Quit),
+            (0, UserAction::KeyDown(1)),
+            (2, UserAction::KeyUp(1)),
+        ]);
+
+        let polls = (0..4).map(|_| input.poll_events()).collect::<Vec<_>>();
+
+        assert_eq!(
+            polls,
+            vec![
+                vec![UserAction::KeyDown(1)],
+                vec![],
+                vec![UserAction::Quit, UserAction::KeyUp(1)],
+                vec![],
+            ]
+        );
     }
 }
@@ -0,0 +1,192 @@
+use chipeyte_interpreter::{errors::ChipeyteError, interface::Color};
+
+/// The colours used to present the display.
+///
+/// Colours are indexed by the bitmask of lit planes: index 0 is the background, 1 and 2 are
+/// pixels lit on only the first or second XO-CHIP plane and 3 is pixels lit on both. Two-colour
+/// palettes use the foreground colour for all three.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Palette {
+    pub name: String,
+    pub colors: [Color; 4],
+}
+
+/// Names of the built-in palettes, in the order they are cycled through.
+pub const PALETTE_NAMES: [&str; 6] = [
+    "classic-green",
+    "amber",
+    "lcd",
+    "high-contrast",
+    "octo",
+    "cga",
+];
+
+impl Palette {
+    /// Builds a palette from either two (background and foreground) or four colours.
+    pub fn from_colors(name: &str, colors: &[Color]) -> Result<Palette, String> {
+        let colors = match colors {
+            [bg, fg] => [bg.clone(), fg.clone(), fg.clone(), fg.clone()],
+            [bg, first, second, both] => [bg.clone(), first.clone(), second.clone(), both.clone()],
+            _ => {
+                return Err(format!(
+                    "A palette needs 2 or 4 colours, got {}",
+                    colors.len()
+                ))
+            }
+        };
+
+        Ok(Palette {
+            name: name.to_string(),
+            colors,
+        })
+    }
+
+    /// Looks up a built-in palette by name.
+    pub fn named(name: &str) -> Option<Palette> {
+        let hex: &[&str] = match name {
+            "classic-green" => &["#000000", "#00ff00"],
+            "amber" => &["#1a0f00", "#ffb000"],
+            "lcd" => &["#9bbc0f", "#0f380f"],
+            "high-contrast" => &["#000000", "#ffffff"],
+            "octo" => &["#996600", "#ffcc00", "#ff6600", "#662200"],
+            "cga" => &["#000000", "#55ffff", "#ff55ff", "#ffffff"],
+            _ => return None,
+        };
+
+        let colors = hex
+            .iter()
+            .map(|color| color.parse().unwrap())
+            .collect::<Vec<Color>>();
+
+        Palette::from_colors(name, &colors).ok()
+    }
+
+    /// Parses either the name of a built-in palette or a comma-separated list of two or four hex
+    /// colours, e.g. `#000000,#ffb000`.
+    pub fn parse(spec: &str) -> Result<Palette, String> {
+        if let Some(palette) = Palette::named(spec) {
+            return Ok(palette);
+        }
+
+        if !spec.contains('#') && !spec.contains(',') {
+            return Err(format!(
+                "Unknown palette '{}', expected one of {} or a list of hex colours",
+                spec,
+                PALETTE_NAMES.join(", ")
+            ));
+        }
+
+        let colors = spec
+            .split(',')
+            .map(|color| color.parse())
+            .collect::<Result<Vec<Color>, ChipeyteError>>()
+            .map_err(|e| e.to_string())?;
+
+        Palette::from_colors("custom", &colors)
+    }
+
+    /// All built-in palettes, in cycling order.
+    pub fn builtin() -> Vec<Palette> {
+        PALETTE_NAMES
+            .iter()
+            .filter_map(|name| Palette::named(name))
+            .collect()
+    }
+
+    pub fn background(&self) -> &Color {
+        &self.colors[0]
+    }
+
+    /// Colour of a pixel with the given planes lit.
+    pub fn color(&self, planes: u8) -> &Color {
+        &self.colors[(planes & 0b11) as usize]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::named(PALETTE_NAMES[0]).unwrap()
+    }
+}
+
+/// Cycles through a list of palettes, starting with the configured one.
+pub struct PaletteCycle {
+    palettes: Vec<Palette>,
+    current: usize,
+}
+
+impl PaletteCycle {
+    pub fn new(initial: Palette) -> Self {
+        let mut palettes = Palette::builtin();
+
+        let current = match palettes.iter().position(|p| *p == initial) {
+            Some(index) => index,
+            None => {
+                palettes.insert(0, initial);
+                0
+            }
+        };
+
+        Self { palettes, current }
+    }
+
+    pub fn current(&self) -> &Palette {
+        &self.palettes[self.current]
+    }
+
+    /// Switches to the next palette and returns it.
+    pub fn advance(&mut self) -> &Palette {
+        self.current = (self.current + 1) % self.palettes.len();
+        self.current()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_builtin_palette_exists() {
+        assert_eq!(Palette::builtin().len(), PALETTE_NAMES.len());
+    }
+
+    #[test]
+    fn two_colour_palettes_use_the_foreground_for_every_plane() {
+        let palette = Palette::parse("#000000,#ffb000").unwrap();
+
+        assert_eq!(palette.background(), &Color(0, 0, 0));
+        assert_eq!(palette.color(1), &Color(255, 176, 0));
+        assert_eq!(palette.color(3), &Color(255, 176, 0));
+    }
+
+    #[test]
+    fn four_colour_palettes_color_each_plane() {
+        let palette = Palette::named("cga").unwrap();
+
+        assert_eq!(palette.color(1), &Color(0x55, 0xff, 0xff));
+        assert_eq!(palette.color(2), &Color(0xff, 0x55, 0xff));
+        assert_eq!(palette.color(3), &Color(0xff, 0xff, 0xff));
+    }
+
+    #[test]
+    fn parse_rejects_unknown_names_and_bad_colours() {
+        assert!(Palette::parse("sepia").is_err());
+        assert!(Palette::parse("#000000,#zzzzzz").is_err());
+        assert!(Palette::parse("#000000,#111111,#222222").is_err());
+    }
+
+    #[test]
+    fn cycle_starts_with_a_custom_palette_and_wraps_around() {
+        let custom = Palette::parse("#101010,#f0f0f0").unwrap();
+        let mut cycle = PaletteCycle::new(custom.clone());
+
+        assert_eq!(cycle.current(), &custom);
+        assert_eq!(cycle.advance().name, "classic-green");
+
+        for _ in 0..PALETTE_NAMES.len() {
+            cycle.advance();
+        }
+
+        assert_eq!(cycle.current(), &custom);
+    }
+}
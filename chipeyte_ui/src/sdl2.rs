@@ -1,265 +1,525 @@
-use chipeyte_interpreter::interface::{Audible, Color, Controller, Drawable, UserAction};
+use crate::gamepad::GamepadBindings;
+use crate::keymap::Keymap;
+use crate::layout::Viewport;
+use crate::palette::{Palette, PaletteCycle};
+use crate::UIConfig;
+use chipeyte_interpreter::{
+    interface::{Audible, Color, Controller, Drawable, InputSource, RunState, UserAction},
+    postprocess::Frame,
+    sound::{AudioFrame, Beep, ToneGenerator, FRAME_RATE},
+};
 use sdl2::{
     self,
-    audio::{AudioCallback, AudioDevice, AudioSpecDesired},
-    event::Event,
+    audio::{AudioQueue, AudioSpecDesired},
+    controller::GameController,
+    event::{Event, WindowEvent},
     keyboard::Keycode,
     pixels::Color as Sdl2Color,
     rect::Rect,
     render::Canvas,
-    video::Window,
-    EventPump, Sdl,
+    video::{FullscreenType, Window},
+    EventPump, GameControllerSubsystem, Sdl,
 };
-use std::collections::HashSet;
+use std::collections::HashMap;
 
+/// The SDL2 front end, composed of independent screen, input and audio components.
 pub struct Sdl2UI {
     pub screen: Sdl2Screen,
+    pub input: Sdl2Input,
     pub speaker: Sdl2Speaker,
     pub controller: Controller,
 }
 
 impl Sdl2UI {
-    pub fn init(fg_color: Color, bg_color: Color) -> Self {
-        let sdl_context = sdl2::init().unwrap();
-        let screen = Sdl2Screen::init(&sdl_context, fg_color, bg_color);
-        let speaker = Sdl2Speaker::init(&sdl_context);
+    pub fn init(config: UIConfig) -> Result<Self, String> {
+        let sdl_context = sdl2::init()?;
+        let screen = Sdl2Screen::init(&sdl_context, config.title, config.palette, config.scale)?;
+        let input = Sdl2Input::init(&sdl_context, config.keymap, config.gamepad)?;
+        let speaker = Sdl2Speaker::init(&sdl_context, config.beep)?;
         let controller = Controller::new();
 
-        Self {
+        Ok(Self {
             screen,
+            input,
             speaker,
             controller,
-        }
+        })
     }
 }
 
 pub struct Sdl2Screen {
-    event_pump: EventPump,
     canvas: Canvas<Window>,
-    bg_color: Color,
-    fg_color: Color,
-    pixels: HashSet<(u8, u8)>,
-    pixel_size: u32,
+    /// Name of the running program.
+    title: Option<String>,
+    palettes: PaletteCycle,
+    state: RunState,
+    last_frame: Option<Frame>,
+    scale: u32,
+    display_size: (usize, usize),
 }
 
 impl Sdl2Screen {
-    pub fn init(sdl_context: &Sdl, fg_color: Color, bg_color: Color) -> Sdl2Screen {
-        let video_subsystem = sdl_context.video().unwrap();
-
-        // 64 x 32 pixel grid
-        let pixel_size = 10;
-        let width = 64 * pixel_size;
-        let height = 32 * pixel_size;
+    pub fn init(
+        sdl_context: &Sdl,
+        title: Option<String>,
+        palette: Palette,
+        scale: u32,
+    ) -> Result<Sdl2Screen, String> {
+        let video_subsystem = sdl_context.video()?;
+
+        // 64 x 32 pixel grid until the program switches resolution
+        let display_size = (64, 32);
+        let scale = scale.max(1);
 
         let window = video_subsystem
-            .window("Chipeyte", width, height)
+            .window(
+                "Chipeyte",
+                display_size.0 as u32 * scale,
+                display_size.1 as u32 * scale,
+            )
             .position_centered()
+            .resizable()
             .build()
-            .unwrap();
-
-        let mut canvas = window.into_canvas().build().unwrap();
-        let event_pump = sdl_context.event_pump().unwrap();
-        let Color(r, g, b) = bg_color;
-        let background_color = Sdl2Color::RGB(r, g, b);
+            .map_err(|e| e.to_string())?;
 
-        canvas.set_draw_color(background_color);
-        canvas.clear();
-        canvas.present();
+        let canvas = window.into_canvas().build().map_err(|e| e.to_string())?;
 
-        Sdl2Screen {
+        let mut screen = Sdl2Screen {
             canvas,
-            event_pump,
-            fg_color,
-            bg_color,
-            pixel_size,
-            pixels: HashSet::new(),
-        }
+            title,
+            palettes: PaletteCycle::new(palette),
+            state: RunState::default(),
+            last_frame: None,
+            scale,
+            display_size,
+        };
+        screen.update_title();
+        screen.clear();
+        Ok(screen)
+    }
+
+    /// Current placement of the display inside the window.
+    pub fn viewport(&self) -> Viewport {
+        let (width, height) = self.canvas.window().drawable_size();
+
+        Viewport::fit(width, height, self.display_size.0, self.display_size.1)
     }
-}
 
-impl Drawable for Sdl2Screen {
     fn clear(&mut self) {
-        let Color(r, g, b) = self.bg_color;
+        let viewport = self.viewport();
+        let Color(r, g, b) = self.palettes.current().background().clone();
 
-        self.pixels.clear();
-        self.canvas.set_draw_color(Sdl2Color::RGB(r, g, b));
+        self.canvas.set_draw_color(Sdl2Color::RGB(0, 0, 0));
         self.canvas.clear();
+        self.canvas.set_draw_color(Sdl2Color::RGB(r, g, b));
+
+        if let Err(e) = self.canvas.fill_rect(Rect::new(
+            viewport.x,
+            viewport.y,
+            viewport.width,
+            viewport.height,
+        )) {
+            eprintln!("Failed to draw background: {:?}", e);
+        }
+    }
+
+    fn draw(&mut self, frame: &Frame) {
+        let display_size = (frame.width(), frame.height());
+
+        if display_size != self.display_size {
+            self.relayout(display_size);
+        }
+
+        self.clear();
+
+        let viewport = self.viewport();
+        let palette = self.palettes.current();
+
+        for (x, y, planes, intensity) in frame.pixels() {
+            let Color(r, g, b) = blend(palette.background(), palette.color(planes), intensity);
+            self.canvas.set_draw_color(Sdl2Color::RGB(r, g, b));
+
+            let (pos_x, pos_y) = viewport.position(x, y);
+
+            if let Err(e) = self.canvas.fill_rect(Rect::new(
+                pos_x,
+                pos_y,
+                viewport.pixel_size,
+                viewport.pixel_size,
+            )) {
+                eprintln!("Failed to draw pixel: {:?}", e);
+            }
+        }
         self.canvas.present();
     }
 
-    fn remove_pixel(&mut self, x: u8, y: u8) {
-        self.pixels.remove(&(x, y));
+    /// Shows the program, the palette and anything unusual about the emulator state in the title,
+    /// e.g. `Chipeyte - Sound test - amber (paused, 150%)`.
+    fn update_title(&mut self) {
+        let mut title = match &self.title {
+            Some(name) => format!("Chipeyte - {} - {}", name, self.palettes.current().name),
+            None => format!("Chipeyte - {}", self.palettes.current().name),
+        };
+        let state = self.state.to_string();
+
+        if !state.is_empty() {
+            title = format!("{} ({})", title, state);
+        }
+
+        if let Err(e) = self.canvas.window_mut().set_title(&title) {
+            eprintln!("Failed to set window title: {:?}", e);
+        }
     }
 
-    fn has_pixel(&self, x: u8, y: u8) -> bool {
-        self.pixels.contains(&(x, y))
+    /// Adapts the window to a new display resolution, keeping the configured scale unless the
+    /// window is fullscreen.
+    fn relayout(&mut self, display_size: (usize, usize)) {
+        self.display_size = display_size;
+
+        let window = self.canvas.window_mut();
+
+        if window.fullscreen_state() == FullscreenType::Off {
+            let width = display_size.0 as u32 * self.scale;
+            let height = display_size.1 as u32 * self.scale;
+
+            if let Err(e) = window.set_size(width, height) {
+                eprintln!("Failed to resize window: {:?}", e);
+            }
+        }
     }
+}
 
-    fn add_pixel(&mut self, x: u8, y: u8) {
-        self.pixels.insert((x, y));
+impl Drawable for Sdl2Screen {
+    fn render(&mut self, frame: &Frame) {
+        self.draw(frame);
+        self.last_frame = Some(frame.clone());
     }
 
-    fn render(&mut self) {
-        let Color(r, g, b) = self.bg_color;
-        self.canvas.set_draw_color(Sdl2Color::RGB(r, g, b));
-        self.canvas.clear();
+    fn redraw(&mut self) {
+        match self.last_frame.take() {
+            Some(frame) => {
+                self.draw(&frame);
+                self.last_frame = Some(frame);
+            }
+            None => {
+                self.clear();
+                self.canvas.present();
+            }
+        }
+    }
 
-        let Color(r, g, b) = self.fg_color;
-        self.canvas.set_draw_color(Sdl2Color::RGB(r, g, b));
+    fn toggle_fullscreen(&mut self) {
+        let window = self.canvas.window_mut();
 
-        for pixel in self.pixels.iter() {
-            let pos_x = pixel.0 as i32 * self.pixel_size as i32;
-            let pos_y = pixel.1 as i32 * self.pixel_size as i32;
+        let next = match window.fullscreen_state() {
+            FullscreenType::Off => FullscreenType::Desktop,
+            _ => FullscreenType::Off,
+        };
 
-            if let Err(e) =
-                self.canvas
-                    .fill_rect(Rect::new(pos_x, pos_y, self.pixel_size, self.pixel_size))
-            {
-                eprintln!("Failed to draw pixel: {:?}", e);
+        if let Err(e) = window.set_fullscreen(next) {
+            eprintln!("Failed to toggle fullscreen: {:?}", e);
+        }
+
+        self.redraw();
+    }
+
+    fn cycle_palette(&mut self) {
+        self.palettes.advance();
+        self.update_title();
+        self.redraw();
+    }
+
+    fn show_state(&mut self, state: &RunState) {
+        self.state = *state;
+        self.update_title();
+    }
+
+    fn show_unmapped_key(&mut self, name: &str) {
+        eprintln!("No Chip-8 key is mapped to '{}'", name);
+    }
+}
+
+/// Keyboard and game controller input, translated to Chip-8 keys through the keymap and gamepad
+/// bindings.
+pub struct Sdl2Input {
+    event_pump: EventPump,
+    keymap: Keymap,
+    gamepad: GamepadBindings,
+    controller_subsystem: GameControllerSubsystem,
+    /// Open game controllers by joystick instance id, kept open to receive their events.
+    controllers: HashMap<u32, GameController>,
+}
+
+impl Sdl2Input {
+    pub fn init(
+        sdl_context: &Sdl,
+        keymap: Keymap,
+        gamepad: GamepadBindings,
+    ) -> Result<Self, String> {
+        let event_pump = sdl_context.event_pump()?;
+        // Controllers connected at startup are reported as added devices on the first poll
+        let controller_subsystem = sdl_context.game_controller()?;
+
+        Ok(Self {
+            event_pump,
+            keymap,
+            gamepad,
+            controller_subsystem,
+            controllers: HashMap::new(),
+        })
+    }
+
+    fn open_controller(&mut self, joystick_index: u32) {
+        match self.controller_subsystem.open(joystick_index) {
+            Ok(controller) => {
+                self.controllers
+                    .insert(controller.instance_id(), controller);
             }
+            Err(e) => eprintln!("Failed to open game controller: {:?}", e),
         }
-        self.canvas.present();
     }
+}
 
-    fn poll_events(&mut self) -> Option<UserAction> {
-        self.event_pump.poll_iter().fold(None, |result, event| {
-            result.or_else(move || match event {
+impl InputSource for Sdl2Input {
+    fn poll_events(&mut self) -> Vec<UserAction> {
+        let events = self.event_pump.poll_iter().collect::<Vec<Event>>();
+        let mut input = Vec::new();
+
+        for event in events {
+            let action = match event {
                 Event::Quit { .. }
                 | Event::KeyDown {
                     keycode: Some(Keycode::Escape),
                     ..
-                } => {
-                    return Some(UserAction::Quit);
-                }
+                } => Some(UserAction::Quit),
+                Event::Window {
+                    win_event: WindowEvent::SizeChanged(_, _),
+                    ..
+                } => Some(UserAction::Redraw),
+                Event::KeyDown { repeat: true, .. } => None,
                 Event::KeyDown {
                     keycode: Some(key), ..
-                } => return Some(UserAction::KeyDown(translate_key(&key))),
+                } => hotkey(key).or_else(|| match self.keymap.key(&key.name()) {
+                    Some(key) => Some(UserAction::KeyDown(key)),
+                    None => Some(UserAction::UnmappedKey(key.name())),
+                }),
                 Event::KeyUp {
                     keycode: Some(key), ..
-                } => return Some(UserAction::KeyUp(translate_key(&key))),
-
-                _ => {
-                    return None;
+                } => self.keymap.key(&key.name()).map(UserAction::KeyUp),
+                Event::ControllerDeviceAdded { which, .. } => {
+                    self.open_controller(which);
+                    None
                 }
-            })
-        })
-    }
+                Event::ControllerDeviceRemoved { which, .. } => {
+                    self.controllers.remove(&which);
+                    None
+                }
+                Event::ControllerButtonDown { .. } | Event::ControllerButtonUp { .. } => {
+                    translate_button(&event, &self.gamepad)
+                }
+
+                _ => None,
+            };
 
-    fn get_pixels(&self) -> HashSet<(u8, u8)> {
-        self.pixels.clone()
+            if let Some(action) = action {
+                input.push(action);
+            }
+        }
+
+        input
     }
 }
 
-/// Translate Sdl2 keycode to Chipeyte key.
-///
-/// Original Chip-8 layout
-///
-///   ,---------------.
-///   | 1 | 2 | 3 | C |
-///   |---|---|---|---|
-///   | 4 | 5 | 6 | D |
-///   |---|---|---|---|
-///   | 7 | 8 | 9 | E |
-///   |---|---|---|---|
-///   | A | 0 | B | F |
-///   `---------------´
-///
-/// Modern keyboard layout:
-///
-///   ,---------------.
-///   | 6 | 7 | 8 | 9 |
-///   |---|---|---|---|
-///   | Y | U | I | O |
-///   |---|---|---|---|
-///   | H | J | K | L |
-///   |---|---|---|---|
-///   | N | M | , | . |
-///   `---------------´
-fn translate_key(key: &Keycode) -> Option<u8> {
+/// Emulator controls, kept on function keys so they never clash with a keymap.
+fn hotkey(key: Keycode) -> Option<UserAction> {
     match key {
-        Keycode::Num6 => Some(1),
-        Keycode::Num7 => Some(2),
-        Keycode::Num8 => Some(3),
-        Keycode::Num9 => Some(12),
-
-        Keycode::Y => Some(4),
-        Keycode::U => Some(5),
-        Keycode::I => Some(6),
-        Keycode::O => Some(13),
-
-        Keycode::H => Some(7),
-        Keycode::J => Some(8),
-        Keycode::K => Some(9),
-        Keycode::L => Some(14),
-
-        Keycode::N => Some(10),
-        Keycode::M => Some(0),
-        Keycode::Comma => Some(11),
-        Keycode::Period => Some(15),
+        Keycode::F2 => Some(UserAction::CyclePalette),
+        Keycode::F3 => Some(UserAction::Reset),
+        Keycode::F5 | Keycode::Pause => Some(UserAction::Pause),
+        Keycode::F6 => Some(UserAction::FrameAdvance),
+        Keycode::F7 => Some(UserAction::SlowDown),
+        Keycode::F8 => Some(UserAction::SpeedUp),
+        Keycode::F9 => Some(UserAction::ToggleTurbo),
+        Keycode::F11 => Some(UserAction::ToggleFullscreen),
         _ => None,
     }
 }
 
-struct SquareWave {
-    phase_inc: f32,
-    phase: f32,
-    volume: f32,
+/// Translates a game controller button event to the Chip-8 key bound to the button.
+fn translate_button(event: &Event, bindings: &GamepadBindings) -> Option<UserAction> {
+    match event {
+        Event::ControllerButtonDown { button, .. } => {
+            bindings.key(&button.string()).map(UserAction::KeyDown)
+        }
+        Event::ControllerButtonUp { button, .. } => {
+            bindings.key(&button.string()).map(UserAction::KeyUp)
+        }
+        _ => None,
+    }
 }
 
-impl AudioCallback for SquareWave {
-    type Channel = f32;
+/// Mixes `to` into `from` by `intensity`, where 255 gives `to` unchanged.
+fn blend(from: &Color, to: &Color, intensity: u8) -> Color {
+    let mix = |a: u8, b: u8| {
+        let (a, b, t) = (a as u32, b as u32, intensity as u32);
+        ((a * (255 - t) + b * t) / 255) as u8
+    };
 
-    fn callback(&mut self, out: &mut [Self::Channel]) {
-        for x in out.iter_mut() {
-            *x = if self.phase <= 0.5 {
-                self.volume
-            } else {
-                -self.volume
-            };
-            self.phase = (self.phase + self.phase_inc) % 1.0;
-        }
-    }
+    Color(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
 }
 
+/// Plays the sound of each emulated frame by rendering its samples into an SDL audio queue.
+///
+/// The queue is kept a few frames ahead of playback. It is topped up with silence when it runs dry
+/// and frames are dropped when emulation runs ahead, e.g. in turbo mode.
 pub struct Sdl2Speaker {
-    audio_device: AudioDevice<SquareWave>,
+    queue: AudioQueue<f32>,
+    generator: ToneGenerator,
+    samples: Vec<f32>,
 }
 
+/// Bounds of the queued audio, in frames.
+const MIN_QUEUED_FRAMES: u32 = 2;
+const MAX_QUEUED_FRAMES: u32 = 6;
+
 impl Sdl2Speaker {
-    pub fn init(sdl_context: &Sdl) -> Self {
-        let audio_subsystem = sdl_context.audio().unwrap();
+    pub fn init(sdl_context: &Sdl, beep: Beep) -> Result<Self, String> {
+        let audio_subsystem = sdl_context.audio()?;
 
         let desired_spec = AudioSpecDesired {
             freq: Some(44_100),
             channels: Some(1),
-            samples: None,
+            samples: Some(512),
         };
 
-        let audio_device = audio_subsystem
-            .open_playback(None, &desired_spec, |spec| SquareWave {
-                phase_inc: 440.0 / spec.freq as f32,
-                phase: 0.0,
-                volume: 0.25,
-            })
-            .unwrap();
+        let queue = audio_subsystem.open_queue::<f32, _>(None, &desired_spec)?;
+        let generator = ToneGenerator::new(beep, queue.spec().freq as u32);
 
-        Self { audio_device }
+        queue.resume();
+
+        Ok(Self {
+            queue,
+            generator,
+            samples: Vec::new(),
+        })
+    }
+
+    /// Number of whole frames waiting to be played.
+    fn queued_frames(&self) -> u32 {
+        let frame_size = self.queue.spec().freq as u32 / FRAME_RATE * 4;
+
+        self.queue.size() / frame_size.max(1)
+    }
+
+    fn queue_samples(&mut self) {
+        if !self.queue.queue(&self.samples) {
+            eprintln!("Failed to queue audio: {}", sdl2::get_error());
+        }
+        self.samples.clear();
     }
 }
 
 impl Audible for Sdl2Speaker {
-    fn play_sound(&self) {
-        self.audio_device.resume();
-    }
+    fn play_frame(&mut self, frame: &AudioFrame) {
+        let queued = self.queued_frames();
+
+        if queued >= MAX_QUEUED_FRAMES {
+            return;
+        }
+
+        for _ in queued..MIN_QUEUED_FRAMES {
+            self.generator
+                .render_frame(&AudioFrame::default(), &mut self.samples);
+        }
 
-    fn stop_sound(&self) {
-        self.audio_device.pause();
+        self.generator.render_frame(frame, &mut self.samples);
+        self.queue_samples();
     }
+}
 
-    fn is_playing(&self) -> bool {
-        match self.audio_device.status() {
-            sdl2::audio::AudioStatus::Playing => true,
-            _ => false,
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chipeyte_interpreter::{
+        framebuffer::{FrameBuffer, Resolution},
+        postprocess::{Filter, PostProcessor},
+    };
+    use sdl2::controller::Button;
+    use std::env;
+
+    fn button_event(button: Button, down: bool) -> Event {
+        if down {
+            Event::ControllerButtonDown {
+                timestamp: 0,
+                which: 0,
+                button,
+            }
+        } else {
+            Event::ControllerButtonUp {
+                timestamp: 0,
+                which: 0,
+                button,
+            }
         }
     }
+
+    #[test]
+    fn function_keys_are_emulator_hotkeys() {
+        assert_eq!(hotkey(Keycode::F5), Some(UserAction::Pause));
+        assert_eq!(hotkey(Keycode::F3), Some(UserAction::Reset));
+        assert_eq!(hotkey(Keycode::Q), None);
+    }
+
+    #[test]
+    fn controller_buttons_translate_to_bound_keys() {
+        let bindings = GamepadBindings::default();
+
+        assert!(matches!(
+            translate_button(&button_event(Button::DPadUp, true), &bindings),
+            Some(UserAction::KeyDown(0x2))
+        ));
+        assert!(matches!(
+            translate_button(&button_event(Button::A, false), &bindings),
+            Some(UserAction::KeyUp(0x5))
+        ));
+        assert!(translate_button(&button_event(Button::Guide, true), &bindings).is_none());
+    }
+
+    #[test]
+    fn controller_bindings_can_be_changed() {
+        let mut bindings = GamepadBindings::empty();
+        bindings.bind("rightshoulder", 0xb);
+
+        assert!(matches!(
+            translate_button(&button_event(Button::RightShoulder, true), &bindings),
+            Some(UserAction::KeyDown(0xb))
+        ));
+        assert!(translate_button(&button_event(Button::DPadUp, true), &bindings).is_none());
+    }
+
+    // Uses SDL's dummy video driver so it runs without a display. All checks share one test as
+    // SDL can only be initialised once at a time.
+    #[test]
+    fn screen_scales_letterboxes_and_relayouts() {
+        env::set_var("SDL_VIDEODRIVER", "dummy");
+
+        let sdl_context = sdl2::init().unwrap();
+        let mut screen = Sdl2Screen::init(&sdl_context, None, Palette::default(), 4).unwrap();
+
+        assert_eq!(screen.canvas.window().size(), (256, 128));
+        assert_eq!(screen.viewport().pixel_size, 4);
+
+        let mut frame_buffer = FrameBuffer::new();
+        frame_buffer.set_resolution(Resolution::High);
+        let mut processor = PostProcessor::new(Filter::None);
+        screen.render(processor.process(&frame_buffer));
+
+        assert_eq!(screen.canvas.window().size(), (512, 256));
+
+        screen.canvas.window_mut().set_size(1000, 300).unwrap();
+        let viewport = screen.viewport();
+
+        assert_eq!(viewport.pixel_size, 4);
+        assert_eq!((viewport.x, viewport.y), (244, 22));
+    }
 }
@@ -0,0 +1,299 @@
+use chipeyte_interpreter::{
+    quirks::PROFILE_NAMES,
+    timing::TIMING_NAMES,
+    trace::{Trigger, FORMAT_NAMES},
+    PROGRAM_START,
+};
+use clap::{Args, Parser, Subcommand};
+use std::path::PathBuf;
+
+/// Highest address of the 4 KB address space.
+const MAX_ADDRESS: u16 = 0x0fff;
+
+/// Chipeyte - a Chip-8 emulator
+///
+/// Runs the given ROM when no command is given.
+#[derive(Debug, Parser)]
+#[command(
+    name = "chipeyte",
+    version,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run a ROM (the default)
+    Run(Box<RunArgs>),
+
+    /// Print a linear disassembly of a ROM
+    Disasm(DisasmArgs),
+
+    /// Analyse a ROM and report its code, data, subroutines and the variant it needs
+    Info(InfoArgs),
+
+    /// Export the control flow graph of a ROM as a Graphviz DOT file
+    Cfg(CfgArgs),
+
+    /// Compare two JSON Lines traces and report where they first diverge
+    TraceDiff(TraceDiffArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    /// Path to the ROM
+    #[arg(required = true)]
+    pub rom: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub machine: MachineArgs,
+
+    /// Palette name, or a list of two or four hex colours separated by commas
+    #[arg(long, value_name = "PALETTE")]
+    pub palette: Option<String>,
+
+    /// Initial size of a Chip-8 pixel in screen pixels
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub scale: Option<u32>,
+
+    /// Keymap preset: classic, modern or numpad
+    #[arg(long, value_name = "PRESET")]
+    pub keymap: Option<String>,
+
+    /// Run without a window or sound, as fast as possible, and print the final display
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Number of frames to run in headless mode
+    #[arg(long, value_name = "N", default_value_t = 600, requires = "headless")]
+    pub frames: u64,
+
+    /// Write the sound of a headless run to FILE as a WAV file
+    #[arg(long, value_name = "FILE", requires = "headless")]
+    pub export_wav: Option<PathBuf>,
+
+    /// Print the interpreter state when the program stops
+    #[arg(long)]
+    pub debug: bool,
+
+    #[command(flatten)]
+    pub trace: TraceArgs,
+
+    #[command(flatten)]
+    pub profile: ProfileArgs,
+
+    /// Write the addresses executed, read and written to FILE when the program stops
+    #[arg(long, value_name = "FILE")]
+    pub coverage: Option<PathBuf>,
+}
+
+/// Options for profiling where the program spends its instructions.
+#[derive(Debug, Args)]
+pub struct ProfileArgs {
+    /// Print the hottest addresses, operations and subroutines when the program stops
+    #[arg(long = "profile")]
+    pub report: bool,
+
+    /// Write the instructions executed per call stack to FILE, for flamegraph tools
+    #[arg(long, value_name = "FILE")]
+    pub flamegraph: Option<PathBuf>,
+}
+
+/// Options for tracing the instructions executed.
+#[derive(Debug, Args)]
+pub struct TraceArgs {
+    /// Write the address, opcode and effects of every instruction executed to FILE
+    #[arg(long = "trace", value_name = "FILE")]
+    pub path: Option<PathBuf>,
+
+    /// Trace format: aligned text, or JSON Lines for tools
+    #[arg(long = "trace-format", value_name = "FORMAT", value_parser = FORMAT_NAMES,
+          default_value = "text", requires = "path")]
+    pub format: String,
+
+    /// Start tracing on reaching pc=ADDR, pc=ADDR-ADDR (hex) or cycle=N
+    #[arg(long = "trace-start", value_name = "TRIGGER", value_parser = parse_trigger,
+          requires = "path")]
+    pub start: Option<Trigger>,
+
+    /// Stop tracing after reaching pc=ADDR, pc=ADDR-ADDR (hex) or cycle=N
+    #[arg(long = "trace-stop", value_name = "TRIGGER", value_parser = parse_trigger,
+          requires = "path")]
+    pub stop: Option<Trigger>,
+}
+
+/// Options shaping the emulated machine.
+#[derive(Debug, Args)]
+pub struct MachineArgs {
+    /// Chip-8 dialect of the program, which decides the default quirks
+    #[arg(long, value_name = "VARIANT", value_parser = PROFILE_NAMES)]
+    pub variant: Option<String>,
+
+    /// Quirks of the emulated interpreter, defaulting to those of the variant or none
+    #[arg(long, value_name = "PROFILE", value_parser = PROFILE_NAMES)]
+    pub quirks: Option<String>,
+
+    /// Clock speed in instructions per second
+    #[arg(long, value_name = "HZ", conflicts_with = "ipf",
+          value_parser = clap::value_parser!(u32).range(60..=1_000_000))]
+    pub clock: Option<u32>,
+
+    /// Instructions per 60 Hz frame
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..=100_000))]
+    pub ipf: Option<u32>,
+
+    /// Instruction timing: fixed by the clock speed, or vip for the cycles of the COSMAC VIP
+    #[arg(long, value_name = "TIMING", value_parser = TIMING_NAMES)]
+    pub timing: Option<String>,
+
+    /// Seed for the random number generator, for reproducible runs
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u64>,
+
+    /// Address to load and start the program at, in hex
+    #[arg(long, value_name = "ADDR", value_parser = parse_address, default_value = "200")]
+    pub load_address: u16,
+}
+
+#[derive(Debug, Args)]
+pub struct DisasmArgs {
+    /// Path to the ROM
+    pub rom: PathBuf,
+
+    /// Address the program is loaded at, in hex
+    #[arg(long, value_name = "ADDR", value_parser = parse_address, default_value = "200")]
+    pub load_address: u16,
+
+    /// Coverage file of a run, to mark the bytes executed, read and written
+    #[arg(long, value_name = "FILE")]
+    pub coverage: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    /// Path to the ROM
+    pub rom: PathBuf,
+
+    /// Address the program is loaded at and starts from, in hex
+    #[arg(long, value_name = "ADDR", value_parser = parse_address, default_value = "200")]
+    pub load_address: u16,
+}
+
+#[derive(Debug, Args)]
+pub struct CfgArgs {
+    /// Path to the ROM
+    pub rom: PathBuf,
+
+    /// Address the program is loaded at and starts from, in hex
+    #[arg(long, value_name = "ADDR", value_parser = parse_address, default_value = "200")]
+    pub load_address: u16,
+
+    /// Coverage file of a run, to colour the blocks that were executed
+    #[arg(long, value_name = "FILE")]
+    pub coverage: Option<PathBuf>,
+
+    /// File to write the graph to instead of standard output
+    #[arg(short, long, value_name = "FILE")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct TraceDiffArgs {
+    /// Trace written by `--trace FILE --trace-format jsonl`
+    pub ours: PathBuf,
+
+    /// Trace to compare with, such as one from another emulator in the same format
+    pub theirs: PathBuf,
+}
+
+/// Parses a hex address such as `200` or `0x600` within the 4 KB address space.
+fn parse_address(input: &str) -> Result<u16, String> {
+    let digits = input.trim_start_matches("0x").trim_start_matches("0X");
+
+    match u16::from_str_radix(digits, 16) {
+        Ok(address) if address <= MAX_ADDRESS => Ok(address),
+        _ => Err(format!(
+            "expected a hex address from 0 to {:x}, e.g. {:x}",
+            MAX_ADDRESS, PROGRAM_START
+        )),
+    }
+}
+
+fn parse_trigger(input: &str) -> Result<Trigger, String> {
+    input.parse().map_err(|e| format!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn cli_is_well_formed() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn a_bare_rom_runs_it() {
+        let cli = Cli::try_parse_from(["chipeyte", "--seed", "3", "game.ch8"]).unwrap();
+
+        assert!(cli.command.is_none());
+        assert_eq!(cli.run.rom, Some(PathBuf::from("game.ch8")));
+        assert_eq!(cli.run.machine.seed, Some(3));
+        assert_eq!(cli.run.machine.load_address, PROGRAM_START);
+    }
+
+    #[test]
+    fn tracing_takes_a_file_format_and_triggers() {
+        let cli = Cli::try_parse_from([
+            "chipeyte",
+            "--trace",
+            "game.trace",
+            "--trace-format",
+            "jsonl",
+            "--trace-start",
+            "pc=300-3ff",
+            "game.ch8",
+        ])
+        .unwrap();
+
+        assert_eq!(cli.run.trace.path, Some(PathBuf::from("game.trace")));
+        assert_eq!(cli.run.trace.format, "jsonl");
+        assert_eq!(cli.run.trace.start, Some(Trigger::Pc(0x300, 0x3ff)));
+        assert_eq!(cli.run.trace.stop, None);
+    }
+
+    #[test]
+    fn subcommands_take_their_own_arguments() {
+        let cli =
+            Cli::try_parse_from(["chipeyte", "disasm", "--load-address", "0x600", "game.ch8"])
+                .unwrap();
+
+        match cli.command {
+            Some(Command::Disasm(args)) => assert_eq!(args.load_address, 0x600),
+            other => panic!("Expected disasm, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_conflicting_and_out_of_range_options() {
+        assert!(Cli::try_parse_from(["chipeyte", "--clock", "600", "--ipf", "10", "a"]).is_err());
+        assert!(Cli::try_parse_from(["chipeyte", "--load-address", "1000", "a"]).is_err());
+        assert!(Cli::try_parse_from(["chipeyte", "--quirks", "chip48", "a"]).is_err());
+        assert!(Cli::try_parse_from(["chipeyte", "--timing", "pal", "a"]).is_err());
+        assert!(Cli::try_parse_from(["chipeyte", "--frames", "10", "a"]).is_err());
+        assert!(Cli::try_parse_from(["chipeyte", "--export-wav", "a.wav", "a"]).is_err());
+        assert!(Cli::try_parse_from(["chipeyte"]).is_err());
+        assert!(Cli::try_parse_from(["chipeyte", "--trace-start", "cycle=5", "a"]).is_err());
+        assert!(
+            Cli::try_parse_from(["chipeyte", "--trace", "t", "--trace-stop", "5", "a"]).is_err()
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! The control flow graph written by `chipeyte cfg`, in Graphviz's DOT language.
+
+use chipeyte_interpreter::{
+    analysis::{Block, EdgeKind, Graph},
+    coverage::Coverage,
+};
+use std::collections::BTreeSet;
+use std::fmt::Write;
+
+/// Fill colours of blocks that ran fully, partly and not at all.
+const EXECUTED: &str = "palegreen";
+const PARTLY_EXECUTED: &str = "khaki";
+const UNEXECUTED: &str = "lightpink";
+
+/// Writes the graph with a node per block listing its instructions, colouring the blocks by
+/// whether they ran when coverage is given.
+pub fn render(name: &str, graph: &Graph, coverage: Option<&Coverage>) -> String {
+    let mut dot = String::new();
+
+    writeln!(dot, "digraph \"{}\" {{", name.replace('"', "\\\"")).unwrap();
+    writeln!(dot, "    node [shape=box, fontname=\"monospace\"];").unwrap();
+
+    for block in graph.blocks.values() {
+        let label: String = block
+            .instructions
+            .iter()
+            .map(|(address, op)| format!("{:04x}  {}\\l", address, op))
+            .collect();
+        let fill = match coverage {
+            Some(coverage) => format!(", style=filled, fillcolor={}", colour(block, coverage)),
+            None => String::new(),
+        };
+
+        writeln!(
+            dot,
+            "    b{:03x} [label=\"{}\"{}];",
+            block.start, label, fill
+        )
+        .unwrap();
+    }
+
+    // Several edges may lead to the same address outside the ROM, which gets a single node
+    let outside: BTreeSet<u16> = graph
+        .edges
+        .iter()
+        .filter_map(|edge| edge.to)
+        .filter(|to| !graph.blocks.contains_key(to))
+        .collect();
+
+    for to in outside {
+        writeln!(
+            dot,
+            "    x{:03x} [label=\"{:03x} (outside the ROM)\", shape=plaintext];",
+            to, to
+        )
+        .unwrap();
+    }
+
+    for edge in &graph.edges {
+        let to = match edge.to {
+            Some(to) if graph.blocks.contains_key(&to) => format!("b{:03x}", to),
+            Some(to) => format!("x{:03x}", to),
+            None => {
+                writeln!(
+                    dot,
+                    "    u{:03x} [label=\"?\", shape=plaintext];",
+                    edge.from
+                )
+                .unwrap();
+                format!("u{:03x}", edge.from)
+            }
+        };
+
+        match attributes(edge.kind) {
+            "" => writeln!(dot, "    b{:03x} -> {};", edge.from, to),
+            attributes => writeln!(dot, "    b{:03x} -> {} [{}];", edge.from, to, attributes),
+        }
+        .unwrap();
+    }
+
+    writeln!(dot, "}}").unwrap();
+    dot
+}
+
+fn colour(block: &Block, coverage: &Coverage) -> &'static str {
+    let executed = block
+        .instructions
+        .iter()
+        .filter(|(address, _)| coverage.executed.contains(address))
+        .count();
+
+    match executed {
+        0 => UNEXECUTED,
+        n if n == block.instructions.len() => EXECUTED,
+        _ => PARTLY_EXECUTED,
+    }
+}
+
+fn attributes(kind: EdgeKind) -> &'static str {
+    match kind {
+        EdgeKind::FallThrough => "",
+        EdgeKind::Skip => "style=dashed, label=\"skip\"",
+        EdgeKind::Jump => "color=blue",
+        EdgeKind::Call => "color=darkgreen, label=\"call\"",
+        EdgeKind::Return => "color=darkgreen, style=dotted, label=\"ret\"",
+        EdgeKind::Unresolved => "color=red, style=dashed, label=\"unresolved\"",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chipeyte_interpreter::analysis::Analysis;
+
+    #[test]
+    fn renders_blocks_edges_and_coverage() {
+        // 0x200: SE V0, 0
+        // 0x202: JP V0, 0x300
+        // 0x204: JP 0x800
+        let program = vec![0x30, 0x00, 0xb3, 0x00, 0x18, 0x00];
+        let graph = Graph::of(&program, &Analysis::of(&program, 0x200));
+        let coverage = Coverage::parse("0200 x\n0201 x\n0204 x\n").unwrap();
+
+        let dot = render("game.ch8", &graph, Some(&coverage));
+
+        assert!(dot.starts_with("digraph \"game.ch8\" {\n"));
+        assert!(dot.contains(
+            "    b200 [label=\"0200  SE V0, 0x00\\l\", style=filled, fillcolor=palegreen];\n"
+        ));
+        assert!(dot.contains("fillcolor=lightpink"));
+        assert!(dot.contains("    b200 -> b204 [style=dashed, label=\"skip\"];\n"));
+        assert!(dot.contains("    b202 -> u202 [color=red, style=dashed, label=\"unresolved\"];\n"));
+        assert!(dot.contains("    b204 -> x800 [color=blue];\n"));
+        assert!(dot.ends_with("}\n"));
+    }
+
+    #[test]
+    fn declares_an_address_outside_the_rom_once() {
+        // 0x200: SE V0, 0
+        // 0x202: JP 0x800
+        // 0x204: JP 0x800
+        let program = vec![0x30, 0x00, 0x18, 0x00, 0x18, 0x00];
+        let graph = Graph::of(&program, &Analysis::of(&program, 0x200));
+
+        let dot = render("game.ch8", &graph, None);
+
+        assert_eq!(dot.matches("    x800 [").count(), 1);
+        assert!(dot.contains("    b202 -> x800 [color=blue];\n"));
+        assert!(dot.contains("    b204 -> x800 [color=blue];\n"));
+    }
+}
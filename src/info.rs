@@ -0,0 +1,151 @@
+//! The report printed by `chipeyte info`.
+
+use crate::program_reader::Rom;
+use chipeyte_interpreter::analysis::Analysis;
+use std::fmt::Write;
+
+/// Describes the ROM and the results of analysing it.
+pub fn report(name: &str, rom: &Rom, analysis: &Analysis) -> String {
+    let mut report = String::new();
+    let mut line = |label: &str, value: String| {
+        writeln!(report, "{:<16}{}", format!("{}:", label), value).unwrap();
+    };
+
+    line("File", name.to_string());
+
+    if let Some(entry) = &rom.entry {
+        line("Title", entry.title.clone());
+
+        if let Some(author) = &entry.author {
+            line("Author", author.clone());
+        }
+    }
+
+    line("Size", format!("{} bytes", rom.program.len()));
+    line("SHA-1", rom.sha1.clone());
+    line("Variant", rom.variant.to_string());
+    line("Code requires", analysis.variant().to_string());
+
+    let code = analysis.code_regions();
+    let data = analysis.data_regions();
+    line("Code", format!("{} bytes{}", bytes(&code), regions(&code)));
+    line("Data", format!("{} bytes{}", bytes(&data), regions(&data)));
+
+    line(
+        "Subroutines",
+        match analysis.max_call_depth {
+            Some(depth) => format!(
+                "{}, calls nested {} deep",
+                analysis.subroutines.len(),
+                depth
+            ),
+            None => format!("{}, calls nested recursively", analysis.subroutines.len()),
+        },
+    );
+
+    line(
+        "Operations",
+        analysis
+            .categories()
+            .iter()
+            .map(|(category, count)| format!("{} {}", count, category.name()))
+            .collect::<Vec<String>>()
+            .join(", "),
+    );
+
+    line(
+        "Jumps outside",
+        list(
+            analysis
+                .external_targets
+                .iter()
+                .map(|(from, to)| format!("{:03x} -> {:03x}", from, to)),
+        ),
+    );
+    line(
+        "SYS calls",
+        list(
+            analysis
+                .sys_calls
+                .iter()
+                .map(|address| format!("{:03x}", address)),
+        ),
+    );
+    line(
+        "Indirect jumps",
+        list(
+            analysis
+                .indirect_jumps
+                .iter()
+                .map(|address| format!("{:03x}", address)),
+        ),
+    );
+
+    report
+}
+
+fn bytes(regions: &[(u16, u16)]) -> usize {
+    regions
+        .iter()
+        .map(|(start, end)| (end - start) as usize)
+        .sum()
+}
+
+fn regions(regions: &[(u16, u16)]) -> String {
+    match regions.len() {
+        0 => String::new(),
+        _ => format!(
+            " at {}",
+            regions
+                .iter()
+                .map(|(start, end)| format!("{:03x}-{:03x}", start, end - 1))
+                .collect::<Vec<String>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn list<I>(items: I) -> String
+where
+    I: Iterator<Item = String>,
+{
+    let items = items.collect::<Vec<String>>();
+
+    if items.is_empty() {
+        "none".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chipeyte_interpreter::quirks::Variant;
+
+    #[test]
+    fn report_describes_code_data_and_calls() {
+        // 0x200: CALL 0x206
+        // 0x202: SYS 0x123
+        // 0x204: JP 0x204
+        // 0x206: RET
+        // 0x208: data
+        let program = vec![0x22, 0x06, 0x01, 0x23, 0x12, 0x04, 0x00, 0xee, 0xff];
+        let rom = Rom {
+            sha1: "sha1".to_string(),
+            entry: None,
+            variant: Variant::Chip8,
+            program,
+        };
+        let analysis = Analysis::of(&rom.program, 0x200);
+
+        let report = report("game.ch8", &rom, &analysis);
+
+        assert!(report.contains("Code:           8 bytes at 200-207\n"));
+        assert!(report.contains("Data:           1 bytes at 208-208\n"));
+        assert!(report.contains("Subroutines:    1, calls nested 1 deep\n"));
+        assert!(report.contains("Operations:     4 flow\n"));
+        assert!(report.contains("SYS calls:      202\n"));
+        assert!(report.contains("Jumps outside:  none\n"));
+    }
+}
@@ -0,0 +1,95 @@
+//! The disassembly annotated with coverage, printed by `chipeyte disasm --coverage`.
+
+use chipeyte_interpreter::{
+    analysis::{length, Analysis},
+    coverage::Coverage,
+    disassembler::Instruction,
+};
+use std::{collections::BTreeSet, fmt::Write};
+
+/// Prefixes each instruction with what the run did to its bytes, `x` for executed, `r` for read
+/// and `w` for written, and ends with how much of the reachable code ran.
+pub fn annotate(instructions: &[Instruction], analysis: &Analysis, coverage: &Coverage) -> String {
+    let mut listing = String::new();
+
+    for instruction in instructions {
+        let bytes = [instruction.address, instruction.address + 1];
+        let flag = |flag: char, addresses: &BTreeSet<u16>| {
+            if bytes.iter().any(|address| addresses.contains(address)) {
+                flag
+            } else {
+                '-'
+            }
+        };
+
+        writeln!(
+            listing,
+            "{}{}{}  {}",
+            flag('x', &coverage.executed),
+            flag('r', &coverage.read),
+            flag('w', &coverage.written),
+            instruction
+        )
+        .unwrap();
+    }
+
+    let code = analysis.code_bytes();
+    let executed = code
+        .iter()
+        .filter(|address| coverage.executed.contains(address))
+        .count();
+    let missed = analysis
+        .code
+        .iter()
+        .filter(|(address, op)| {
+            (**address..**address + length(op)).all(|byte| !coverage.executed.contains(&byte))
+        })
+        .map(|(address, _)| format!("{:04x}", address))
+        .collect::<Vec<String>>();
+
+    writeln!(
+        listing,
+        "\nExecuted {} of {} reachable code bytes ({:.1}%)",
+        executed,
+        code.len(),
+        100.0 * executed as f64 / code.len().max(1) as f64
+    )
+    .unwrap();
+
+    if !missed.is_empty() {
+        writeln!(listing, "Never executed: {}", missed.join(", ")).unwrap();
+    }
+
+    listing
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chipeyte_interpreter::disassembler::disassemble;
+
+    #[test]
+    fn marks_bytes_and_lists_unexecuted_code() {
+        // 0x200: SE V0, 0
+        // 0x202: LD [I], V0
+        // 0x204: JP 0x204
+        let program = vec![0x30, 0x00, 0xf0, 0x55, 0x12, 0x04];
+        let coverage = Coverage::parse("0200 x\n0201 x\n0204 xw\n0205 x\n").unwrap();
+
+        let listing = annotate(
+            &disassemble(&program, 0x200),
+            &Analysis::of(&program, 0x200),
+            &coverage,
+        );
+
+        assert_eq!(
+            listing,
+            "x--  0200  3000  SE V0, 0x00\n\
+             ---  0202  f055  LD [I], V0\n\
+             x-w  0204  1204  JP 0x204\n\
+             \n\
+             Executed 4 of 6 reachable code bytes (66.7%)\n\
+             Never executed: 0202\n"
+        );
+    }
+}
@@ -1,34 +1,415 @@
 //! An emulation of the Chip-8 programming langauge
 
+mod cli;
+mod dot;
+mod info;
+mod listing;
+mod profile_report;
 mod program_reader;
+mod rom_database;
+mod settings;
+mod trace_diff;
 
-use chipeyte_interpreter::{interface::Color, ChipeyteInterpreter, Config};
-use std::env;
-use std::path::Path;
+use chipeyte_interpreter::{
+    analysis::{Analysis, Graph},
+    coverage::Coverage,
+    disassembler,
+    errors::ChipeyteError,
+    framebuffer::FrameBuffer,
+    interface::Audible,
+    sound::{self, Beep, ToneGenerator},
+    trace::{TraceWindow, TraceWriter},
+    ChipeyteInterpreter, Config, Status,
+};
+use chipeyte_ui::{mock::MockUI, UIConfig};
+use clap::Parser;
+use cli::{
+    CfgArgs, Cli, Command, DisasmArgs, InfoArgs, MachineArgs, RunArgs, TraceArgs, TraceDiffArgs,
+};
+use settings::{KeymapSetting, PaletteSetting, Profile, QuirksSetting};
+use std::{
+    fmt, fs,
+    io::BufWriter,
+    path::{Path, PathBuf},
+    process,
+};
 
 #[cfg(not(feature = "sdl2-ui"))]
 use chipeyte_ui::mock::MockUI as UI;
 #[cfg(feature = "sdl2-ui")]
 use chipeyte_ui::sdl2::Sdl2UI as UI;
 
+/// First line of the coverage files written, explaining the flags.
+const COVERAGE_HEADER: &str = "# Chipeyte coverage: x executed, r read, w written\n";
+
+/// Sample rate of the WAV files written by `--export-wav`.
+const WAV_SAMPLE_RATE: u32 = 44_100;
+
+/// Why Chipeyte stopped early. Each kind exits with its own code; clap exits with 2 on bad usage.
+enum Failure {
+    /// The ROM could not be read or does not fit in memory.
+    Rom(String),
+    /// The configuration file or an option value is invalid.
+    Settings(String),
+    /// The window or sound could not be opened.
+    Ui(String),
+    /// The program made the interpreter fail.
+    Emulation(ChipeyteError),
+    /// A file other than the ROM could not be read or written.
+    File(String),
+}
+
+impl Failure {
+    fn exit_code(&self) -> i32 {
+        match self {
+            Failure::Rom(_) => 3,
+            Failure::Settings(_) => 4,
+            Failure::Ui(_) => 5,
+            Failure::Emulation(_) => 6,
+            Failure::File(_) => 7,
+        }
+    }
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Rom(message) | Failure::Settings(message) | Failure::File(message) => {
+                write!(f, "{}", message)
+            }
+            Failure::Ui(message) => write!(f, "Cannot open the display: {}", message),
+            Failure::Emulation(error) => write!(f, "The program failed: {}", error),
+        }
+    }
+}
+
 fn main() {
     #[cfg(feature = "logging")]
     env_logger::init();
 
-    let args: Vec<String> = env::args().collect();
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Some(Command::Run(args)) => run(*args),
+        Some(Command::Disasm(args)) => disasm(args),
+        Some(Command::Info(args)) => info(args),
+        Some(Command::Cfg(args)) => cfg(args),
+        Some(Command::TraceDiff(args)) => trace_diff(args),
+        None => run(cli.run),
+    };
 
-    if args.len() < 2 {
-        panic!("Need to pass a file argument!");
+    if let Err(failure) = result {
+        eprintln!("chipeyte: {}", failure);
+        process::exit(failure.exit_code());
     }
+}
 
-    let program = program_reader::read(Path::new(&args[1]));
+fn run(args: RunArgs) -> Result<(), Failure> {
+    let rom = args.rom.as_deref().expect("clap requires a ROM");
+    let rom = program_reader::read(rom, args.machine.load_address).map_err(Failure::Rom)?;
 
-    let mut ui = UI::init(Color(0, 255, 0), Color(0, 0, 0));
+    let settings = settings::load().map_err(Failure::Settings)?;
+    let profile = command_line_profile(&args).or(settings.profile(&rom.sha1, rom.profile()));
 
-    let mut interpreter = ChipeyteInterpreter::new(Config::default());
+    let beep = match &settings.beep {
+        Some(setting) => setting.to_beep(),
+        None => Ok(Beep::default()),
+    }
+    .map_err(Failure::Settings)?;
+
+    let ui_config = UIConfig {
+        title: rom.title().map(String::from),
+        palette: profile.to_palette().map_err(Failure::Settings)?,
+        scale: args.scale.unwrap_or_else(|| UIConfig::default().scale),
+        keymap: profile.to_keymap().map_err(Failure::Settings)?,
+        gamepad: profile.to_gamepad().map_err(Failure::Settings)?,
+        beep,
+    };
+
+    let config = machine_config(&args.machine, &profile).map_err(Failure::Settings)?;
+    let mut interpreter = ChipeyteInterpreter::new(config);
 
-    interpreter.run(&mut ui.screen, &ui.speaker, &mut ui.controller, &program);
+    if let Some(writer) = trace_writer(&args.trace)? {
+        interpreter.trace_to(writer);
+    }
+
+    if args.profile.report || args.profile.flamegraph.is_some() {
+        interpreter.start_profiling();
+    }
+
+    if args.coverage.is_some() {
+        interpreter.start_coverage();
+    }
+
+    let result = if args.headless {
+        run_headless(
+            &mut interpreter,
+            ui_config,
+            &rom.program,
+            args.frames,
+            args.export_wav.as_deref(),
+        )
+    } else {
+        let mut ui = UI::init(ui_config).map_err(Failure::Ui)?;
+
+        interpreter
+            .run(
+                &mut ui.screen,
+                &mut ui.input,
+                &mut ui.speaker,
+                &mut ui.controller,
+                &rom.program,
+            )
+            .map_err(Failure::Emulation)
+    };
+
+    if args.debug {
+        eprintln!("{}", interpreter);
+    }
 
     #[cfg(feature = "logging")]
     log::debug!("{}", interpreter);
+
+    if let Some(profile) = interpreter.profile() {
+        if args.profile.report {
+            eprint!("{}", profile_report::report(profile));
+        }
+
+        if let Some(path) = &args.profile.flamegraph {
+            fs::write(path, profile_report::folded(profile))
+                .map_err(|e| Failure::File(format!("Cannot write {:?}: {}", path, e)))?;
+        }
+    }
+
+    if let (Some(path), Some(coverage)) = (&args.coverage, interpreter.coverage()) {
+        fs::write(path, format!("{}{}", COVERAGE_HEADER, coverage))
+            .map_err(|e| Failure::File(format!("Cannot write {:?}: {}", path, e)))?;
+    }
+
+    result
+}
+
+/// Runs `frames` frames without waiting between them and prints the final display, writing the
+/// sound to `wav` if given.
+fn run_headless(
+    interpreter: &mut ChipeyteInterpreter,
+    ui_config: UIConfig,
+    program: &[u8],
+    frames: u64,
+    wav: Option<&Path>,
+) -> Result<(), Failure> {
+    let mut generator = ToneGenerator::new(ui_config.beep, WAV_SAMPLE_RATE);
+    let mut samples = Vec::new();
+    let mut ui = MockUI::init(ui_config).map_err(Failure::Ui)?;
+    interpreter.load(program);
+
+    for _ in 0..frames {
+        let status = interpreter
+            .run_frame(&mut ui.controller)
+            .map_err(Failure::Emulation)?;
+
+        ui.speaker.play_frame(interpreter.audio_frame());
+
+        if wav.is_some() {
+            generator.render_frame(interpreter.audio_frame(), &mut samples);
+        }
+
+        if status == Status::Halted {
+            break;
+        }
+    }
+
+    if let Some(path) = wav {
+        let file = fs::File::create(path)
+            .map_err(|e| Failure::File(format!("Cannot write {:?}: {}", path, e)))?;
+
+        sound::write_wav(&mut BufWriter::new(file), WAV_SAMPLE_RATE, &samples)
+            .map_err(|e| Failure::File(format!("Cannot write {:?}: {}", path, e)))?;
+    }
+
+    print_display(interpreter.frame_buffer());
+    Ok(())
+}
+
+fn print_display(frame_buffer: &FrameBuffer) {
+    for y in 0..frame_buffer.height() {
+        let row = (0..frame_buffer.width())
+            .map(|x| if frame_buffer.is_lit(x, y) { '#' } else { '.' })
+            .collect::<String>();
+
+        println!("{}", row);
+    }
+}
+
+/// The settings given on the command line, which take precedence over the configuration file.
+fn command_line_profile(args: &RunArgs) -> Profile {
+    Profile {
+        variant: args.machine.variant.clone(),
+        quirks: args.machine.quirks.clone().map(QuirksSetting::Profile),
+        clock: args.machine.clock,
+        ipf: args.machine.ipf,
+        timing: args.machine.timing.clone(),
+        palette: args.palette.clone().map(PaletteSetting::Named),
+        keymap: args.keymap.clone().map(KeymapSetting::Preset),
+        ..Profile::default()
+    }
+}
+
+fn machine_config(args: &MachineArgs, profile: &Profile) -> Result<Config, String> {
+    let (clock_speed, instructions_per_frame) = profile.to_speed()?;
+
+    Ok(Config {
+        clock_speed,
+        instructions_per_frame,
+        seed: args.seed,
+        quirks: profile.to_quirks()?,
+        load_address: args.load_address,
+        timing: profile.to_timing()?,
+        ..Config::default()
+    })
+}
+
+fn trace_writer(args: &TraceArgs) -> Result<Option<TraceWriter>, Failure> {
+    let path = match &args.path {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+    let file = fs::File::create(path)
+        .map_err(|e| Failure::File(format!("Cannot write {:?}: {}", path, e)))?;
+    let format = args
+        .format
+        .parse()
+        .map_err(|e| Failure::Settings(format!("{}", e)))?;
+
+    Ok(Some(TraceWriter::new(
+        Box::new(BufWriter::new(file)),
+        format,
+        TraceWindow {
+            start: args.start,
+            stop: args.stop,
+        },
+    )))
+}
+
+fn disasm(args: DisasmArgs) -> Result<(), Failure> {
+    let rom = program_reader::read(&args.rom, args.load_address).map_err(Failure::Rom)?;
+    let instructions = disassembler::disassemble(&rom.program, args.load_address);
+
+    match &args.coverage {
+        Some(path) => print!(
+            "{}",
+            listing::annotate(
+                &instructions,
+                &Analysis::of(&rom.program, args.load_address),
+                &read_coverage(path)?
+            )
+        ),
+        None => {
+            for instruction in instructions {
+                println!("{}", instruction);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn info(args: InfoArgs) -> Result<(), Failure> {
+    let rom = program_reader::read(&args.rom, args.load_address).map_err(Failure::Rom)?;
+    let analysis = Analysis::of(&rom.program, args.load_address);
+
+    print!(
+        "{}",
+        info::report(&args.rom.display().to_string(), &rom, &analysis)
+    );
+
+    Ok(())
+}
+
+fn cfg(args: CfgArgs) -> Result<(), Failure> {
+    let rom = program_reader::read(&args.rom, args.load_address).map_err(Failure::Rom)?;
+    let graph = Graph::of(&rom.program, &Analysis::of(&rom.program, args.load_address));
+
+    let coverage = args.coverage.as_deref().map(read_coverage).transpose()?;
+
+    let name = args.rom.display().to_string();
+    let dot = dot::render(&name, &graph, coverage.as_ref());
+
+    match &args.output {
+        Some(path) => fs::write(path, dot)
+            .map_err(|e| Failure::File(format!("Cannot write {:?}: {}", path, e))),
+        None => {
+            print!("{}", dot);
+            Ok(())
+        }
+    }
+}
+
+fn read_coverage(path: &Path) -> Result<Coverage, Failure> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| Failure::File(format!("Cannot read {:?}: {}", path, e)))?;
+
+    Coverage::parse(&text).map_err(|e| Failure::File(format!("{:?}: {}", path, e)))
+}
+
+/// Prints the first divergence and exits with 1, like `diff`, if the traces differ.
+fn trace_diff(args: TraceDiffArgs) -> Result<(), Failure> {
+    let read = |path: &PathBuf| {
+        fs::read_to_string(path)
+            .map_err(|e| Failure::File(format!("Cannot read {:?}: {}", path, e)))
+    };
+
+    match trace_diff::diff(&read(&args.ours)?, &read(&args.theirs)?).map_err(Failure::File)? {
+        Ok(matched) => {
+            println!("Traces match over {} instructions", matched);
+            Ok(())
+        }
+        Err(divergence) => {
+            print!("{}", divergence);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chipeyte_interpreter::quirks::{Quirks, Variant};
+    use settings::Settings;
+
+    fn config(arguments: &[&str]) -> Config {
+        let args = Cli::try_parse_from(arguments).unwrap().run;
+        let rom =
+            program_reader::read(args.rom.as_deref().unwrap(), args.machine.load_address).unwrap();
+        let profile =
+            command_line_profile(&args).or(Settings::default().profile(&rom.sha1, rom.profile()));
+
+        machine_config(&args.machine, &profile).unwrap()
+    }
+
+    #[test]
+    fn the_database_profile_reaches_the_machine() {
+        let config = config(&["chipeyte", "programs/drawing02.c8"]);
+
+        assert_eq!(config.instructions_per_frame, Some(15));
+        assert_eq!(
+            config.quirks,
+            Quirks {
+                display_wait: false,
+                ..Variant::Chip8.quirks()
+            }
+        );
+    }
+
+    #[test]
+    fn the_command_line_overrides_the_database_profile() {
+        let clocked = config(&["chipeyte", "--clock", "600", "programs/drawing02.c8"]);
+
+        assert_eq!(clocked.instructions_per_frame, None);
+        assert!(clocked.clock_speed.is_some());
+
+        let schip = config(&["chipeyte", "--variant", "schip", "programs/drawing03.c8"]);
+
+        assert_eq!(schip.quirks, Variant::Schip.quirks());
+    }
 }
@@ -0,0 +1,153 @@
+//! The hot-spot report printed by `--profile` and the stacks written by `--flamegraph`.
+
+use chipeyte_interpreter::profile::Profile;
+use std::fmt::Write;
+
+/// Number of addresses listed in the report.
+const HOT_ADDRESSES: usize = 20;
+
+/// Lists the hottest addresses, the categories of operations and the subroutines by the
+/// instructions they take, in total and per frame.
+pub fn report(profile: &Profile) -> String {
+    let mut report = String::new();
+    let total = profile.instructions.max(1) as f64;
+    let frames = profile.frames.max(1) as f64;
+    let share = |count: u64| 100.0 * count as f64 / total;
+    let per_frame = |count: u64| count as f64 / frames;
+
+    writeln!(
+        report,
+        "{} instructions over {} frames, {:.1} per frame",
+        profile.instructions,
+        profile.frames,
+        per_frame(profile.instructions)
+    )
+    .unwrap();
+
+    writeln!(report, "\nHottest addresses:").unwrap();
+    writeln!(
+        report,
+        "  {:<8}{:>12}{:>8}{:>11}  Instruction",
+        "Address", "Count", "%", "Per frame"
+    )
+    .unwrap();
+
+    for (address, count, op) in profile.hot_addresses().iter().take(HOT_ADDRESSES) {
+        writeln!(
+            report,
+            "  {:<8}{:>12}{:>7.1}%{:>11.1}  {}",
+            format!("{:04x}", address),
+            count,
+            share(*count),
+            per_frame(*count),
+            op
+        )
+        .unwrap();
+    }
+
+    writeln!(report, "\nOperations:").unwrap();
+
+    for (category, count) in profile.categories() {
+        writeln!(
+            report,
+            "  {:<12}{:>12}{:>7.1}%",
+            category.name(),
+            count,
+            share(count)
+        )
+        .unwrap();
+    }
+
+    writeln!(report, "\nSubroutines:").unwrap();
+    writeln!(
+        report,
+        "  {:<8}{:>8}{:>12}{:>8}{:>12}{:>8}{:>11}",
+        "Entry", "Calls", "Inclusive", "%", "Exclusive", "%", "Per frame"
+    )
+    .unwrap();
+
+    for subroutine in profile.subroutines() {
+        writeln!(
+            report,
+            "  {:<8}{:>8}{:>12}{:>7.1}%{:>12}{:>7.1}%{:>11.1}",
+            name(profile, subroutine.entry),
+            subroutine.calls,
+            subroutine.inclusive,
+            share(subroutine.inclusive),
+            subroutine.exclusive,
+            share(subroutine.exclusive),
+            per_frame(subroutine.inclusive)
+        )
+        .unwrap();
+    }
+
+    report
+}
+
+/// The stacks in the folded format read by `flamegraph.pl` and `inferno-flamegraph`, one line per
+/// stack with its frames separated by `;` and the number of instructions it executed.
+pub fn folded(profile: &Profile) -> String {
+    profile
+        .stacks()
+        .iter()
+        .map(|(stack, count)| {
+            let frames = stack
+                .iter()
+                .map(|entry| name(profile, *entry))
+                .collect::<Vec<String>>()
+                .join(";");
+
+            format!("{} {}\n", frames, count)
+        })
+        .collect()
+}
+
+fn name(profile: &Profile, entry: u16) -> String {
+    if entry == profile.origin {
+        "main".to_string()
+    } else {
+        format!("{:04x}", entry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chipeyte_interpreter::{interface::Controller, ChipeyteInterpreter, Config};
+
+    // 0x200: CALL 0x204
+    // 0x202: JP 0x200
+    // 0x204: ADD V0, 1
+    // 0x206: RET
+    const PROGRAM: [u8; 8] = [0x22, 0x04, 0x12, 0x00, 0x70, 0x01, 0x00, 0xee];
+
+    fn profile() -> Profile {
+        let mut interpreter = ChipeyteInterpreter::new(Config {
+            instructions_per_frame: Some(8),
+            ..Config::default()
+        });
+        interpreter.start_profiling();
+        interpreter.load(&PROGRAM);
+        interpreter.run_frame(&mut Controller::new()).unwrap();
+
+        interpreter.profile().unwrap().clone()
+    }
+
+    #[test]
+    fn report_lists_addresses_categories_and_subroutines() {
+        let report = report(&profile());
+
+        assert!(report.starts_with("8 instructions over 1 frames, 8.0 per frame\n"));
+        assert!(report.contains("  0200               2   25.0%        2.0  CALL 0x204\n"));
+        assert!(report.contains("  flow                   6   75.0%\n"));
+        assert!(report
+            .contains("  main           0           8  100.0%           4   50.0%        8.0\n"));
+        assert!(report
+            .contains("  0204           2           4   50.0%           4   50.0%        4.0\n"));
+    }
+
+    #[test]
+    fn folded_stacks_name_the_entry_point_main() {
+        assert_eq!(folded(&profile()), "main 4\nmain;0204 4\n");
+    }
+}
@@ -1,10 +1,127 @@
+use crate::rom_database::{self, RomEntry};
+use crate::settings::Profile;
+use chipeyte_interpreter::{analysis::Analysis, quirks::Variant};
 use std::fs;
 use std::path::Path;
 
-pub fn read(path: &Path) -> Vec<u8> {
-    if !path.exists() {
-        panic!("File {:?} does not exist!", path);
+/// Size of the Chip-8 address space.
+const MEMORY_SIZE: usize = 0x1000;
+
+/// A program read from disk.
+pub struct Rom {
+    pub program: Vec<u8>,
+    pub sha1: String,
+    /// The program's entry in the built-in database, if it is a known program.
+    pub entry: Option<RomEntry>,
+    /// The variant the program is written for, from the database or guessed from the operations
+    /// of its reachable code.
+    pub variant: Variant,
+}
+
+impl Rom {
+    pub fn title(&self) -> Option<&str> {
+        self.entry.as_ref().map(|entry| entry.title.as_str())
+    }
+
+    /// The recommended settings for the program, which include the guessed variant of unknown
+    /// programs that need more than Chip-8.
+    pub fn profile(&self) -> Profile {
+        let mut profile = self
+            .entry
+            .as_ref()
+            .map(|entry| entry.profile.clone())
+            .unwrap_or_default();
+
+        if profile.variant.is_none() && self.variant > Variant::Chip8 {
+            profile.variant = Some(self.variant.to_string());
+        }
+
+        profile
     }
+}
+
+/// Reads the program at `path`, checking that it fits in memory when loaded at `load_address`,
+/// and looks it up in the ROM database.
+pub fn read(path: &Path, load_address: u16) -> Result<Rom, String> {
+    let program = fs::read(path).map_err(|e| format!("Cannot read {:?}: {}", path, e))?;
+
+    rom(program, load_address).map_err(|e| format!("{:?}: {}", path, e))
+}
+
+fn rom(program: Vec<u8>, load_address: u16) -> Result<Rom, String> {
+    check(&program, load_address)?;
+
+    let sha1 = sha1(&program);
+    let entry = rom_database::lookup(&sha1);
+    let variant = entry
+        .as_ref()
+        .and_then(|entry| entry.profile.to_variant().ok().flatten())
+        .unwrap_or_else(|| Analysis::of(&program, load_address).variant());
+
+    Ok(Rom {
+        program,
+        sha1,
+        entry,
+        variant,
+    })
+}
+
+fn check(program: &[u8], load_address: u16) -> Result<(), String> {
+    let space = MEMORY_SIZE.saturating_sub(load_address as usize);
 
-    fs::read(path).expect("Something went wrong when reading the file!")
+    if program.is_empty() {
+        Err("The program is empty".to_string())
+    } else if program.len() > space {
+        Err(format!(
+            "The program is {} bytes but only {} fit in memory from {:03x}",
+            program.len(),
+            space,
+            load_address
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// SHA-1 hex digest of a program, used to identify ROMs regardless of their file name.
+pub fn sha1(program: &[u8]) -> String {
+    sha1_smol::Sha1::from(program).digest().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sha1_returns_the_hex_digest() {
+        assert_eq!(sha1(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    }
+
+    #[test]
+    fn read_looks_up_known_programs() {
+        let rom = read(Path::new("programs/sound.c8"), 0x200).unwrap();
+
+        assert_eq!(rom.title(), Some("Sound test"));
+        assert_eq!(rom.variant, Variant::Chip8);
+        assert_eq!(rom.profile().variant.as_deref(), Some("chip8"));
+    }
+
+    #[test]
+    fn sprite_data_does_not_count_towards_the_variant() {
+        // 0x200: LD I, 0x206
+        // 0x202: DRW V0, V1, 2
+        // 0x204: JP 0x204
+        // 0x206: a sprite that reads as HIGH
+        let rom = rom(vec![0xa2, 0x06, 0xd0, 0x12, 0x12, 0x04, 0x00, 0xff], 0x200).unwrap();
+
+        assert_eq!(rom.variant, Variant::Chip8);
+        assert_eq!(rom.profile().variant, None);
+    }
+
+    #[test]
+    fn check_rejects_empty_and_oversized_programs() {
+        assert!(check(&[], 0x200).is_err());
+        assert!(check(&[0; 0xe00], 0x200).is_ok());
+        assert!(check(&[0; 0xe01], 0x200).is_err());
+    }
 }
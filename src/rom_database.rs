@@ -0,0 +1,72 @@
+//! Metadata of known programs, shipped with Chipeyte and keyed by the SHA-1 of the ROM.
+
+use crate::settings::Profile;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const DATABASE: &str = include_str!("../data/roms.toml");
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RomEntry {
+    pub title: String,
+    pub author: Option<String>,
+    /// Recommended settings for the program.
+    #[serde(flatten)]
+    pub profile: Profile,
+}
+
+#[derive(Debug, Deserialize)]
+struct Database {
+    #[serde(default)]
+    rom: HashMap<String, RomEntry>,
+}
+
+/// Looks up the program with the given SHA-1 hex digest.
+pub fn lookup(sha1: &str) -> Option<RomEntry> {
+    database()
+        .rom
+        .iter()
+        .find(|(digest, _)| digest.eq_ignore_ascii_case(sha1))
+        .map(|(_, entry)| entry.clone())
+}
+
+/// The built-in database, parsed on first use.
+fn database() -> &'static Database {
+    static PARSED: OnceLock<Database> = OnceLock::new();
+
+    PARSED.get_or_init(|| parse(DATABASE).expect("The built-in ROM database is valid"))
+}
+
+fn parse(contents: &str) -> Result<Database, String> {
+    toml::from_str(contents).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_entry_has_valid_settings() {
+        for (sha1, entry) in &database().rom {
+            let profile = &entry.profile;
+
+            assert_eq!(sha1.len(), 40, "{}", entry.title);
+            assert!(profile.to_quirks().is_ok(), "{}", entry.title);
+            assert!(profile.to_speed().is_ok(), "{}", entry.title);
+            assert!(profile.to_palette().is_ok(), "{}", entry.title);
+            assert!(profile.to_keymap().is_ok(), "{}", entry.title);
+            assert!(profile.to_gamepad().is_ok(), "{}", entry.title);
+        }
+    }
+
+    #[test]
+    fn lookup_finds_bundled_programs() {
+        let entry = lookup("FFD905F452DF8E83333ED0C1C6BA29C22A573B36").unwrap();
+
+        assert_eq!(entry.title, "Sound test");
+        assert_eq!(entry.author.as_deref(), Some("Jens Östlund"));
+        assert_eq!(entry.profile.ipf, Some(10));
+        assert!(lookup("da39a3ee5e6b4b0d3255bfef95601890afd80709").is_none());
+    }
+}
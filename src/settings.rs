@@ -0,0 +1,734 @@
+//! User settings read from `$XDG_CONFIG_HOME/chipeyte/config.toml`.
+
+use chipeyte_interpreter::{
+    quirks::{Quirks, Variant, PROFILE_NAMES},
+    sound::Beep,
+    timing::Timing,
+};
+use chipeyte_ui::{
+    gamepad::GamepadBindings,
+    keymap::{Keymap, PRESET_NAMES},
+    palette::Palette,
+};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Allowed clock speeds, in instructions per second.
+pub const CLOCK_RANGE: RangeInclusive<u32> = 60..=1_000_000;
+
+/// Allowed numbers of instructions per frame.
+pub const IPF_RANGE: RangeInclusive<u32> = 1..=100_000;
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Settings {
+    /// Settings for every program, at the top level of the file.
+    #[serde(flatten)]
+    pub defaults: Profile,
+    pub beep: Option<BeepSetting>,
+    /// Overrides for single programs, keyed by the SHA-1 of the ROM, e.g. `[rom."0a1b..."]`.
+    #[serde(default)]
+    pub rom: HashMap<String, Profile>,
+}
+
+impl Settings {
+    /// Overrides for the ROM with the given SHA-1 hex digest.
+    pub fn rom(&self, sha1: &str) -> Option<&Profile> {
+        self.rom
+            .iter()
+            .find(|(digest, _)| digest.eq_ignore_ascii_case(sha1))
+            .map(|(_, profile)| profile)
+    }
+
+    /// The settings for the ROM with the given SHA-1 hex digest: its overrides on top of the
+    /// settings recommended for it, on top of the defaults.
+    pub fn profile(&self, sha1: &str, recommended: Profile) -> Profile {
+        self.rom(sha1)
+            .cloned()
+            .unwrap_or_default()
+            .or(recommended)
+            .or(self.defaults.clone())
+    }
+}
+
+/// Settings that can be given for every program, for a single ROM and on the command line.
+#[derive(Debug, Default, Clone, Deserialize)]
+pub struct Profile {
+    /// The Chip-8 dialect, which decides the default quirks.
+    pub variant: Option<String>,
+    pub quirks: Option<QuirksSetting>,
+    /// Instructions per second.
+    pub clock: Option<u32>,
+    /// Instructions per 60 Hz frame, an alternative to `clock`. Also read as `tickrate`, the name
+    /// used by the CHIP-8 database.
+    #[serde(alias = "tickrate")]
+    pub ipf: Option<u32>,
+    /// `fixed`, or `vip` to time instructions like the COSMAC VIP, which ignores `clock` and `ipf`.
+    pub timing: Option<String>,
+    pub palette: Option<PaletteSetting>,
+    pub keymap: Option<KeymapSetting>,
+    /// Gamepad buttons bound to Chip-8 keys on top of the default bindings, e.g.
+    /// `gamepad = { "a" = "5", "dpup" = "2" }`.
+    #[serde(default)]
+    pub gamepad: HashMap<String, String>,
+}
+
+impl Profile {
+    /// Combines the profile with one of lower precedence, which fills in what this one leaves
+    /// out. Gamepad bindings are merged button by button.
+    pub fn or(self, fallback: Profile) -> Profile {
+        // `clock`, `ipf` and `timing` all set the speed, so they are taken together.
+        let (clock, ipf, timing) =
+            if self.clock.is_some() || self.ipf.is_some() || self.timing.is_some() {
+                (self.clock, self.ipf, self.timing)
+            } else {
+                (fallback.clock, fallback.ipf, fallback.timing)
+            };
+
+        // A variant brings its own quirks, so it replaces a quirk profile named by the fallback.
+        // Single quirks the fallback sets still apply on top of it.
+        let fallback_quirks = match fallback.quirks {
+            Some(QuirksSetting::Profile(_)) if self.variant.is_some() => None,
+            Some(QuirksSetting::Custom {
+                profile: Some(_),
+                vf_reset,
+                memory_increment,
+                shift_vy,
+                jump_vx,
+                clip,
+                display_wait,
+            }) if self.variant.is_some() => Some(QuirksSetting::Custom {
+                profile: None,
+                vf_reset,
+                memory_increment,
+                shift_vy,
+                jump_vx,
+                clip,
+                display_wait,
+            }),
+            quirks => quirks,
+        };
+
+        let mut gamepad = fallback.gamepad;
+        gamepad.extend(self.gamepad);
+
+        Profile {
+            variant: self.variant.or(fallback.variant),
+            quirks: self.quirks.or(fallback_quirks),
+            clock,
+            ipf,
+            timing,
+            palette: self.palette.or(fallback.palette),
+            keymap: self.keymap.or(fallback.keymap),
+            gamepad,
+        }
+    }
+
+    pub fn to_variant(&self) -> Result<Option<Variant>, String> {
+        self.variant
+            .as_ref()
+            .map(|name| name.parse::<Variant>().map_err(|e| e.to_string()))
+            .transpose()
+    }
+
+    /// The quirks to emulate, starting from those of the variant.
+    pub fn to_quirks(&self) -> Result<Quirks, String> {
+        let base = self
+            .to_variant()?
+            .map(|variant| variant.quirks())
+            .unwrap_or_default();
+
+        match &self.quirks {
+            Some(setting) => setting.to_quirks(base),
+            None => Ok(base),
+        }
+    }
+
+    /// The time budget per instruction and the instructions per frame, at most one of them set.
+    pub fn to_speed(&self) -> Result<(Option<Duration>, Option<u32>), String> {
+        match (self.clock, self.ipf) {
+            (Some(_), Some(_)) => Err("Give either clock or ipf, not both".to_string()),
+            (Some(hz), None) if CLOCK_RANGE.contains(&hz) => {
+                Ok((Some(Duration::from_nanos(1_000_000_000 / hz as u64)), None))
+            }
+            (Some(hz), None) => Err(format!(
+                "Clock speed must be between {} and {} Hz, got {}",
+                CLOCK_RANGE.start(),
+                CLOCK_RANGE.end(),
+                hz
+            )),
+            (None, Some(ipf)) if IPF_RANGE.contains(&ipf) => Ok((None, Some(ipf))),
+            (None, Some(ipf)) => Err(format!(
+                "Instructions per frame must be between {} and {}, got {}",
+                IPF_RANGE.start(),
+                IPF_RANGE.end(),
+                ipf
+            )),
+            (None, None) => Ok((None, None)),
+        }
+    }
+
+    pub fn to_timing(&self) -> Result<Timing, String> {
+        match &self.timing {
+            Some(name) => name.parse::<Timing>().map_err(|e| e.to_string()),
+            None => Ok(Timing::Fixed),
+        }
+    }
+
+    pub fn to_palette(&self) -> Result<Palette, String> {
+        match &self.palette {
+            Some(setting) => setting.to_palette(),
+            None => Ok(Palette::default()),
+        }
+    }
+
+    pub fn to_keymap(&self) -> Result<Keymap, String> {
+        match &self.keymap {
+            Some(setting) => setting.to_keymap(),
+            None => Ok(Keymap::default()),
+        }
+    }
+
+    /// The default gamepad bindings with the profile's bindings on top.
+    pub fn to_gamepad(&self) -> Result<GamepadBindings, String> {
+        let mut gamepad = GamepadBindings::default();
+        gamepad.bind_all(&self.gamepad)?;
+
+        Ok(gamepad)
+    }
+}
+
+/// Quirks given either as the name of a variant's profile, e.g. `quirks = "schip"`, or as a table
+/// of single quirks on top of a profile:
+///
+/// ```toml
+/// [quirks]
+/// profile = "chip8"
+/// display_wait = false
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum QuirksSetting {
+    Profile(String),
+    Custom {
+        profile: Option<String>,
+        vf_reset: Option<bool>,
+        memory_increment: Option<bool>,
+        shift_vy: Option<bool>,
+        jump_vx: Option<bool>,
+        clip: Option<bool>,
+        display_wait: Option<bool>,
+    },
+}
+
+impl QuirksSetting {
+    /// The quirks, with `base` used when no profile is named.
+    pub fn to_quirks(&self, base: Quirks) -> Result<Quirks, String> {
+        match self {
+            QuirksSetting::Profile(name) => quirks_profile(name),
+            QuirksSetting::Custom {
+                profile,
+                vf_reset,
+                memory_increment,
+                shift_vy,
+                jump_vx,
+                clip,
+                display_wait,
+            } => {
+                let base = match profile {
+                    Some(name) => quirks_profile(name)?,
+                    None => base,
+                };
+
+                Ok(Quirks {
+                    vf_reset: vf_reset.unwrap_or(base.vf_reset),
+                    memory_increment: memory_increment.unwrap_or(base.memory_increment),
+                    shift_vy: shift_vy.unwrap_or(base.shift_vy),
+                    jump_vx: jump_vx.unwrap_or(base.jump_vx),
+                    clip: clip.unwrap_or(base.clip),
+                    display_wait: display_wait.unwrap_or(base.display_wait),
+                })
+            }
+        }
+    }
+}
+
+fn quirks_profile(name: &str) -> Result<Quirks, String> {
+    Quirks::profile(name).ok_or_else(|| {
+        format!(
+            "Unknown quirk profile '{}', expected one of {}",
+            name,
+            PROFILE_NAMES.join(", ")
+        )
+    })
+}
+
+/// A palette given either by name or as a list of two or four hex colours, e.g.
+/// `palette = "amber"` or `palette = ["#000000", "#ffb000"]`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum PaletteSetting {
+    Named(String),
+    Colors(Vec<String>),
+}
+
+impl PaletteSetting {
+    pub fn to_palette(&self) -> Result<Palette, String> {
+        match self {
+            PaletteSetting::Named(spec) => Palette::parse(spec),
+            PaletteSetting::Colors(colors) => Palette::parse(&colors.join(",")),
+        }
+    }
+}
+
+/// The sound of the beep, any field left out keeping its default:
+///
+/// ```toml
+/// [beep]
+/// frequency = 660
+/// waveform = "triangle"
+/// volume = 0.5
+/// attack = 2    # milliseconds
+/// release = 20  # milliseconds
+/// ```
+#[derive(Debug, Default, Deserialize)]
+pub struct BeepSetting {
+    pub frequency: Option<f32>,
+    pub waveform: Option<String>,
+    pub volume: Option<f32>,
+    pub attack: Option<f32>,
+    pub release: Option<f32>,
+}
+
+impl BeepSetting {
+    pub fn to_beep(&self) -> Result<Beep, String> {
+        let mut beep = Beep::default();
+
+        if let Some(frequency) = self.frequency {
+            if frequency <= 0.0 {
+                return Err(format!(
+                    "Beep frequency must be positive, got {}",
+                    frequency
+                ));
+            }
+            beep.frequency = frequency;
+        }
+
+        if let Some(waveform) = &self.waveform {
+            beep.waveform = waveform.parse().map_err(|e| format!("{}", e))?;
+        }
+
+        if let Some(volume) = self.volume {
+            if !(0.0..=1.0).contains(&volume) {
+                return Err(format!(
+                    "Beep volume must be between 0 and 1, got {}",
+                    volume
+                ));
+            }
+            beep.volume = volume;
+        }
+
+        if let Some(attack) = self.attack {
+            beep.attack = attack.max(0.0) / 1000.0;
+        }
+
+        if let Some(release) = self.release {
+            beep.release = release.max(0.0) / 1000.0;
+        }
+
+        Ok(beep)
+    }
+}
+
+/// A keymap given either as the name of a preset, e.g. `keymap = "classic"`, or as a table of
+/// host keys bound to Chip-8 keys on top of a preset:
+///
+/// ```toml
+/// [keymap]
+/// preset = "numpad"
+/// keys = { "Space" = "5", "Return" = "f" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum KeymapSetting {
+    Preset(String),
+    Custom {
+        preset: Option<String>,
+        #[serde(default)]
+        keys: HashMap<String, String>,
+    },
+}
+
+impl KeymapSetting {
+    pub fn to_keymap(&self) -> Result<Keymap, String> {
+        match self {
+            KeymapSetting::Preset(name) => preset(name),
+            KeymapSetting::Custom { preset: name, keys } => {
+                let mut keymap = match name {
+                    Some(name) => preset(name)?,
+                    None => Keymap::default(),
+                };
+                keymap.name = "custom".to_string();
+                keymap.bind_all(keys)?;
+
+                Ok(keymap)
+            }
+        }
+    }
+}
+
+/// Looks up a built-in keymap, listing the alternatives if there is none by that name.
+pub fn preset(name: &str) -> Result<Keymap, String> {
+    Keymap::preset(name).ok_or_else(|| {
+        format!(
+            "Unknown keymap '{}', expected one of {}",
+            name,
+            PRESET_NAMES.join(", ")
+        )
+    })
+}
+
+/// Location of the configuration file, following the XDG base directory specification.
+pub fn config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+
+    Some(config_home.join("chipeyte").join("config.toml"))
+}
+
+/// Reads the configuration file, falling back to the defaults if it does not exist.
+pub fn load() -> Result<Settings, String> {
+    match config_path() {
+        Some(path) if path.exists() => read(&path),
+        _ => Ok(Settings::default()),
+    }
+}
+
+fn read(path: &Path) -> Result<Settings, String> {
+    let contents = fs::read_to_string(path).map_err(|e| format!("{:?}: {}", path, e))?;
+
+    parse(&contents).map_err(|e| format!("{:?}: {}", path, e))
+}
+
+fn parse(contents: &str) -> Result<Settings, String> {
+    toml::from_str(contents).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chipeyte_interpreter::{interface::Color, sound::Waveform};
+
+    #[test]
+    fn palette_can_be_given_by_name() {
+        let settings = parse(r#"palette = "amber""#).unwrap();
+
+        let palette = settings.defaults.palette.unwrap().to_palette().unwrap();
+
+        assert_eq!(palette, Palette::named("amber").unwrap());
+    }
+
+    #[test]
+    fn palette_can_be_given_as_hex_colours() {
+        let settings = parse(r##"palette = ["#000000", "#ffb000"]"##).unwrap();
+
+        let palette = settings.defaults.palette.unwrap().to_palette().unwrap();
+
+        assert_eq!(palette.color(1), &Color(0xff, 0xb0, 0x00));
+    }
+
+    #[test]
+    fn empty_config_uses_defaults() {
+        let settings = parse("").unwrap();
+
+        assert!(settings.defaults.palette.is_none());
+        assert!(settings.defaults.keymap.is_none());
+        assert!(settings.rom.is_empty());
+    }
+
+    #[test]
+    fn keymap_can_be_given_as_a_preset() {
+        let settings = parse(r#"keymap = "classic""#).unwrap();
+
+        let keymap = settings.defaults.keymap.unwrap().to_keymap().unwrap();
+
+        assert_eq!(keymap, Keymap::preset("classic").unwrap());
+    }
+
+    #[test]
+    fn keymap_bindings_extend_the_preset() {
+        let settings = parse(
+            r#"
+            [keymap]
+            preset = "classic"
+            keys = { "Space" = "5" }
+            "#,
+        )
+        .unwrap();
+
+        let keymap = settings.defaults.keymap.unwrap().to_keymap().unwrap();
+
+        assert_eq!(keymap.key("Space"), Some(5));
+        assert_eq!(keymap.key("W"), Some(5));
+    }
+
+    #[test]
+    fn keymap_can_be_overridden_per_rom() {
+        let settings = parse(
+            r#"
+            keymap = "classic"
+
+            [rom."DA39A3EE5E6B4B0D3255BFEF95601890AFD80709"]
+            keymap = "numpad"
+            "#,
+        )
+        .unwrap();
+
+        let rom = settings
+            .rom("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+            .unwrap();
+
+        assert_eq!(
+            rom.keymap.as_ref().unwrap().to_keymap().unwrap().name,
+            "numpad"
+        );
+        assert!(settings.rom("0000").is_none());
+    }
+
+    #[test]
+    fn gamepad_bindings_can_be_set_globally_and_per_rom() {
+        let settings = parse(
+            r#"
+            gamepad = { "a" = "5" }
+
+            [rom."da39a3ee5e6b4b0d3255bfef95601890afd80709"]
+            gamepad = { "a" = "c", "x" = "1" }
+            "#,
+        )
+        .unwrap();
+
+        let rom = settings
+            .rom("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+            .unwrap();
+
+        assert_eq!(settings.defaults.gamepad["a"], "5");
+        assert_eq!(rom.gamepad["a"], "c");
+        assert_eq!(rom.gamepad["x"], "1");
+    }
+
+    #[test]
+    fn beep_settings_override_the_defaults() {
+        let settings = parse(
+            r#"
+            [beep]
+            waveform = "sine"
+            release = 20
+            "#,
+        )
+        .unwrap();
+
+        let beep = settings.beep.unwrap().to_beep().unwrap();
+
+        assert_eq!(beep.waveform, Waveform::Sine);
+        assert!((beep.release - 0.02).abs() < f32::EPSILON);
+        assert_eq!(beep.frequency, Beep::default().frequency);
+    }
+
+    #[test]
+    fn bad_beep_settings_are_rejected() {
+        let parse_beep = |toml: &str| parse(toml).unwrap().beep.unwrap().to_beep();
+
+        assert!(parse_beep("[beep]\nwaveform = \"pulse\"").is_err());
+        assert!(parse_beep("[beep]\nvolume = 1.5").is_err());
+        assert!(parse_beep("[beep]\nfrequency = 0").is_err());
+    }
+
+    #[test]
+    fn timing_replaces_the_speed_of_the_defaults() {
+        let settings = parse(
+            r#"
+            ipf = 10
+
+            [rom."da39a3ee5e6b4b0d3255bfef95601890afd80709"]
+            timing = "vip"
+            "#,
+        )
+        .unwrap();
+
+        let profile = settings.profile(
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            Profile::default(),
+        );
+
+        assert_eq!(profile.to_timing().unwrap(), Timing::CosmacVip);
+        assert_eq!(profile.to_speed().unwrap(), (None, None));
+
+        let profile = settings.profile("0000", Profile::default());
+
+        assert_eq!(profile.to_timing().unwrap(), Timing::Fixed);
+        assert!(parse(r#"timing = "pal""#)
+            .unwrap()
+            .defaults
+            .to_timing()
+            .is_err());
+    }
+
+    #[test]
+    fn a_variant_replaces_the_quirk_profile_of_the_defaults() {
+        let settings = parse(
+            r#"
+            quirks = "chip8"
+
+            [rom."da39a3ee5e6b4b0d3255bfef95601890afd80709"]
+            variant = "schip"
+            "#,
+        )
+        .unwrap();
+
+        let profile = settings.profile(
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            Profile::default(),
+        );
+
+        assert_eq!(profile.to_quirks().unwrap(), Variant::Schip.quirks());
+
+        let profile = settings.profile("0000", Profile::default());
+
+        assert_eq!(profile.to_quirks().unwrap(), Variant::Chip8.quirks());
+    }
+
+    #[test]
+    fn a_variant_keeps_the_single_quirks_of_the_defaults() {
+        let settings = parse(
+            r#"
+            [quirks]
+            profile = "chip8"
+            clip = false
+            "#,
+        )
+        .unwrap();
+        let rom = Profile {
+            variant: Some("schip".to_string()),
+            ..Profile::default()
+        };
+
+        let quirks = rom.or(settings.defaults).to_quirks().unwrap();
+
+        assert_eq!(
+            quirks,
+            Quirks {
+                clip: false,
+                ..Variant::Schip.quirks()
+            }
+        );
+    }
+
+    #[test]
+    fn unknown_keymap_presets_are_rejected() {
+        let settings = parse(r#"keymap = "dvorak""#).unwrap();
+
+        assert!(settings.defaults.keymap.unwrap().to_keymap().is_err());
+    }
+
+    #[test]
+    fn rom_profiles_override_the_defaults() {
+        let settings = parse(
+            r#"
+            variant = "chip8"
+            palette = "amber"
+            ipf = 10
+
+            [rom."da39a3ee5e6b4b0d3255bfef95601890afd80709"]
+            variant = "schip"
+            clock = 1200
+            "#,
+        )
+        .unwrap();
+
+        let profile = settings.profile(
+            "da39a3ee5e6b4b0d3255bfef95601890afd80709",
+            Profile::default(),
+        );
+
+        assert_eq!(profile.to_quirks().unwrap(), Variant::Schip.quirks());
+        assert_eq!(profile.to_speed().unwrap().1, None);
+        assert_eq!(
+            profile.to_palette().unwrap(),
+            Palette::named("amber").unwrap()
+        );
+
+        let profile = settings.profile("0000", Profile::default());
+
+        assert_eq!(profile.to_quirks().unwrap(), Variant::Chip8.quirks());
+        assert_eq!(profile.to_speed().unwrap(), (None, Some(10)));
+
+        let recommended = Profile {
+            variant: Some("xochip".to_string()),
+            ..Profile::default()
+        };
+        let profile = settings.profile("0000", recommended);
+
+        assert_eq!(profile.to_quirks().unwrap(), Variant::Xochip.quirks());
+        assert_eq!(profile.to_speed().unwrap(), (None, Some(10)));
+    }
+
+    #[test]
+    fn single_quirks_override_the_variant() {
+        let settings = parse(
+            r#"
+            variant = "chip8"
+
+            [quirks]
+            display_wait = false
+            "#,
+        )
+        .unwrap();
+
+        let quirks = settings.defaults.to_quirks().unwrap();
+
+        assert!(!quirks.display_wait);
+        assert!(quirks.vf_reset);
+    }
+
+    #[test]
+    fn command_line_takes_precedence_over_the_config() {
+        let settings = parse(
+            r#"
+            keymap = "classic"
+            quirks = "xochip"
+            gamepad = { "a" = "5" }
+            "#,
+        )
+        .unwrap();
+        let command_line = Profile {
+            quirks: Some(QuirksSetting::Profile("schip".to_string())),
+            gamepad: [("b".to_string(), "6".to_string())]
+                .iter()
+                .cloned()
+                .collect(),
+            ..Profile::default()
+        };
+
+        let profile = command_line.or(settings.profile("0000", Profile::default()));
+
+        assert_eq!(profile.to_quirks().unwrap(), Variant::Schip.quirks());
+        assert_eq!(profile.to_keymap().unwrap().name, "classic");
+        assert_eq!(profile.gamepad.len(), 2);
+    }
+
+    #[test]
+    fn bad_machine_settings_are_rejected() {
+        let profile = |toml: &str| parse(toml).unwrap().defaults;
+
+        assert!(profile(r#"variant = "chip48""#).to_quirks().is_err());
+        assert!(profile(r#"quirks = "chip48""#).to_quirks().is_err());
+        assert!(profile("ipf = 0").to_speed().is_err());
+        assert!(profile("clock = 600\nipf = 10").to_speed().is_err());
+    }
+}
@@ -0,0 +1,360 @@
+//! Comparison of two execution traces, for `chipeyte trace-diff`.
+//!
+//! Both traces are JSON Lines as written by `--trace-format jsonl`, one object per instruction
+//! with hex strings for numbers. Other emulators can be instrumented to write the same format;
+//! only `pc` and `opcode` are required and `registers` may hold every register rather than just
+//! the changed ones. A `PC` in `registers` is skipped, as `pc` already gives it.
+
+use chipeyte_interpreter::trace::Register;
+use serde::Deserialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
+
+/// An instruction of a trace as written in the file.
+#[derive(Debug, Deserialize)]
+struct Line {
+    cycle: Option<u64>,
+    pc: String,
+    opcode: String,
+    op: Option<String>,
+    #[serde(default)]
+    registers: BTreeMap<String, String>,
+    #[serde(default)]
+    memory: BTreeMap<String, String>,
+}
+
+/// An instruction of a trace.
+#[derive(Debug, PartialEq)]
+pub struct Step {
+    pub cycle: Option<u64>,
+    pub pc: u16,
+    pub opcode: u16,
+    pub op: Option<String>,
+    pub registers: BTreeMap<Register, u16>,
+    pub memory: BTreeMap<u16, u8>,
+}
+
+impl Step {
+    fn parse(json: &str) -> Result<Step, String> {
+        let line: Line = serde_json::from_str(json).map_err(|e| e.to_string())?;
+        let registers = line
+            .registers
+            .iter()
+            .filter(|(name, _)| !name.eq_ignore_ascii_case("pc"))
+            .map(|(name, value)| {
+                let register = name.parse().map_err(|e| format!("{}", e))?;
+                let value = match register {
+                    Register::I => hex(value)?,
+                    _ => hex_byte(value)? as u16,
+                };
+
+                Ok((register, value))
+            })
+            .collect::<Result<_, String>>()?;
+        let memory = line
+            .memory
+            .iter()
+            .map(|(address, value)| Ok((hex(address)?, hex_byte(value)?)))
+            .collect::<Result<_, String>>()?;
+
+        Ok(Step {
+            cycle: line.cycle,
+            pc: hex(&line.pc)?,
+            opcode: hex(&line.opcode)?,
+            op: line.op,
+            registers,
+            memory,
+        })
+    }
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(cycle) = self.cycle {
+            write!(f, "cycle {:<8} ", cycle)?;
+        }
+
+        write!(f, "{:04x} {:04x}", self.pc, self.opcode)?;
+
+        if let Some(op) = &self.op {
+            write!(f, " {}", op)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Where two traces first disagree.
+#[derive(Debug, PartialEq)]
+pub enum Divergence {
+    /// The instructions at the same position in both traces differ or have different effects.
+    Step {
+        /// Position of the instruction in the traces, from 0.
+        index: usize,
+        ours: Step,
+        theirs: Step,
+        /// Registers that hold different values afterwards, as (ours, theirs).
+        registers: Vec<(Register, u16, u16)>,
+        /// Bytes stored differently, as (ours, theirs) with `None` for no store.
+        memory: Vec<(u16, Option<u8>, Option<u8>)>,
+    },
+    /// One trace ended after `index` matching instructions while the other went on.
+    Ended {
+        index: usize,
+        /// Whether our trace is the one that ended.
+        ours: bool,
+        next: Step,
+    },
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Divergence::Step {
+                index,
+                ours,
+                theirs,
+                registers,
+                memory,
+            } => {
+                writeln!(f, "First divergence at instruction {}:", index)?;
+                writeln!(f, "  ours:   {}", ours)?;
+                writeln!(f, "  theirs: {}", theirs)?;
+
+                if !registers.is_empty() {
+                    writeln!(f, "Registers after it:")?;
+                }
+
+                for (register, ours, theirs) in registers {
+                    writeln!(
+                        f,
+                        "  {:<3} ours {}  theirs {}",
+                        register.to_string(),
+                        register.format(*ours),
+                        register.format(*theirs)
+                    )?;
+                }
+
+                if !memory.is_empty() {
+                    writeln!(f, "Memory stored by it:")?;
+                }
+
+                for (address, ours, theirs) in memory {
+                    writeln!(
+                        f,
+                        "  {:04x} ours {}  theirs {}",
+                        address,
+                        byte(*ours),
+                        byte(*theirs)
+                    )?;
+                }
+
+                Ok(())
+            }
+            Divergence::Ended { index, ours, next } => {
+                let (ended, other) = if *ours {
+                    ("ours", "theirs")
+                } else {
+                    ("theirs", "ours")
+                };
+
+                writeln!(
+                    f,
+                    "Traces match over {} instructions, then {} ends while {} goes on with:",
+                    index, ended, other
+                )?;
+                writeln!(f, "  {}", next)
+            }
+        }
+    }
+}
+
+/// Compares two traces instruction by instruction, returning the number of instructions compared
+/// or the first divergence.
+///
+/// Registers are compared by the values they hold after each instruction, starting from zero,
+/// so it does not matter whether a trace lists every register or only the changed ones.
+pub fn diff(ours: &str, theirs: &str) -> Result<Result<usize, Divergence>, String> {
+    let mut ours = steps(ours).map(|step| step.map_err(|e| format!("ours: {}", e)));
+    let mut theirs = steps(theirs).map(|step| step.map_err(|e| format!("theirs: {}", e)));
+    let mut our_registers = BTreeMap::new();
+    let mut their_registers = BTreeMap::new();
+    let mut index = 0;
+
+    loop {
+        let (ours, theirs) = match (ours.next().transpose()?, theirs.next().transpose()?) {
+            (None, None) => return Ok(Ok(index)),
+            (Some(next), None) => {
+                return Ok(Err(Divergence::Ended {
+                    index,
+                    ours: false,
+                    next,
+                }))
+            }
+            (None, Some(next)) => {
+                return Ok(Err(Divergence::Ended {
+                    index,
+                    ours: true,
+                    next,
+                }))
+            }
+            (Some(ours), Some(theirs)) => (ours, theirs),
+        };
+
+        our_registers.extend(ours.registers.iter().map(|(r, v)| (*r, *v)));
+        their_registers.extend(theirs.registers.iter().map(|(r, v)| (*r, *v)));
+
+        let registers: Vec<(Register, u16, u16)> = Register::all()
+            .map(|register| {
+                let value = |registers: &BTreeMap<Register, u16>| {
+                    registers.get(&register).copied().unwrap_or(0)
+                };
+
+                (register, value(&our_registers), value(&their_registers))
+            })
+            .filter(|(_, ours, theirs)| ours != theirs)
+            .collect();
+        let memory: Vec<(u16, Option<u8>, Option<u8>)> = ours
+            .memory
+            .keys()
+            .chain(theirs.memory.keys())
+            .collect::<BTreeSet<&u16>>()
+            .into_iter()
+            .map(|address| {
+                (
+                    *address,
+                    ours.memory.get(address).copied(),
+                    theirs.memory.get(address).copied(),
+                )
+            })
+            .filter(|(_, ours, theirs)| ours != theirs)
+            .collect();
+
+        if ours.pc != theirs.pc
+            || ours.opcode != theirs.opcode
+            || !registers.is_empty()
+            || !memory.is_empty()
+        {
+            return Ok(Err(Divergence::Step {
+                index,
+                ours,
+                theirs,
+                registers,
+                memory,
+            }));
+        }
+
+        index += 1;
+    }
+}
+
+fn steps(trace: &str) -> impl Iterator<Item = Result<Step, String>> + '_ {
+    trace
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| Step::parse(line).map_err(|e| format!("line {}: {}", number + 1, e)))
+}
+
+fn hex(digits: &str) -> Result<u16, String> {
+    u16::from_str_radix(digits.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid hex number '{}'", digits))
+}
+
+fn hex_byte(digits: &str) -> Result<u8, String> {
+    u8::from_str_radix(digits.trim_start_matches("0x"), 16)
+        .map_err(|_| format!("invalid hex byte '{}'", digits))
+}
+
+fn byte(value: Option<u8>) -> String {
+    match value {
+        Some(value) => format!("{:02x}", value),
+        None => "--".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OURS: &str = r#"{"cycle":0,"pc":"0200","opcode":"6005","op":"LD V0, 0x05","registers":{"V0":"05"},"memory":{}}
+{"cycle":1,"pc":"0202","opcode":"8014","op":"ADD V0, V1","registers":{"VF":"00"},"memory":{}}
+{"cycle":2,"pc":"0204","opcode":"f055","op":"LD [I], V0","registers":{},"memory":{"0000":"05"}}
+"#;
+
+    #[test]
+    fn identical_traces_match() {
+        assert_eq!(diff(OURS, OURS).unwrap(), Ok(3));
+    }
+
+    #[test]
+    fn full_register_dumps_compare_with_changes() {
+        let theirs = OURS.replace(
+            r#""registers":{"VF":"00"}"#,
+            r#""registers":{"PC":"0204","V0":"05","V1":"00","VF":"00"}"#,
+        );
+
+        assert_eq!(diff(OURS, &theirs).unwrap(), Ok(3));
+    }
+
+    #[test]
+    fn reports_the_first_divergence_with_register_and_memory_deltas() {
+        let theirs = OURS
+            .replace(r#""registers":{"VF":"00"}"#, r#""registers":{"VF":"01"}"#)
+            .replace(r#""0000":"05""#, r#""0001":"05""#);
+
+        match diff(OURS, &theirs).unwrap() {
+            Err(Divergence::Step {
+                index, registers, ..
+            }) => {
+                assert_eq!(index, 1);
+                assert_eq!(registers, vec![(Register::V(0xf), 0, 1)]);
+            }
+            other => panic!("Expected a divergence, got {:?}", other),
+        }
+
+        let theirs = OURS.replace(r#""0000":"05""#, r#""0001":"05""#);
+        let divergence = diff(OURS, &theirs).unwrap().unwrap_err();
+
+        assert_eq!(
+            divergence.to_string(),
+            "First divergence at instruction 2:\n\
+             \x20 ours:   cycle 2        0204 f055 LD [I], V0\n\
+             \x20 theirs: cycle 2        0204 f055 LD [I], V0\n\
+             Memory stored by it:\n\
+             \x20 0000 ours 05  theirs --\n\
+             \x20 0001 ours --  theirs 05\n"
+        );
+    }
+
+    #[test]
+    fn reports_a_trace_ending_early() {
+        let theirs = OURS.lines().take(2).collect::<Vec<&str>>().join("\n");
+
+        match diff(OURS, &theirs).unwrap() {
+            Err(Divergence::Ended { index, ours, next }) => {
+                assert_eq!(index, 2);
+                assert!(!ours);
+                assert_eq!(next.pc, 0x204);
+            }
+            other => panic!("Expected the end of a trace, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_lines() {
+        let error = diff(OURS, "{\"pc\":\"zz\",\"opcode\":\"0000\"}").unwrap_err();
+
+        assert_eq!(error, "theirs: line 1: invalid hex number 'zz'");
+
+        let error = diff(OURS, &OURS.replace(r#""0000":"05""#, r#""0000":"1ff""#)).unwrap_err();
+
+        assert_eq!(error, "theirs: line 3: invalid hex byte '1ff'");
+
+        let error = diff(OURS, &OURS.replace(r#""V0":"05""#, r#""V0":"1ff""#)).unwrap_err();
+
+        assert_eq!(error, "theirs: line 1: invalid hex byte '1ff'");
+    }
+}
@@ -0,0 +1,213 @@
+//! Runs test ROMs headlessly under each quirk profile and compares the final display with the
+//! golden images in `tests/fixtures`.
+//!
+//! The bundled programs and the test ROMs assembled in `suite` always run. Timendus' CHIP-8 test
+//! suite is not bundled: its ROMs are read from the directory named by `CHIPEYTE_TEST_ROMS` by an
+//! ignored test, run with `cargo test -- --ignored`. Its golden images are not checked in yet and
+//! have to be recorded by a first run with the ROMs. Run with `CHIPEYTE_BLESS=1` to record missing
+//! or changed images, which are listed on stderr.
+
+mod suite;
+
+use chipeyte_interpreter::{
+    framebuffer::FrameBuffer,
+    interface::{Controllable, Controller},
+    quirks::Variant,
+    ChipeyteInterpreter, Config, Status,
+};
+use std::{env, fs, path::Path};
+
+/// Frames to run before comparing the display, five seconds of emulated time.
+const FRAMES: u32 = 300;
+/// A speed that lets the test ROMs finish within `FRAMES`.
+const INSTRUCTIONS_PER_FRAME: u32 = 500;
+
+const VARIANTS: [Variant; 3] = [Variant::Chip8, Variant::Schip, Variant::Xochip];
+
+/// A key pressed or released before running a frame.
+type KeyEvent = (u32, u8, bool);
+
+/// Types a key a few frames apart, as the bundled controller program waits for each one.
+const TYPING: [KeyEvent; 6] = [
+    (10, 0x1, true),
+    (11, 0x1, false),
+    (20, 0x2, true),
+    (21, 0x2, false),
+    (30, 0xa, true),
+    (31, 0xa, false),
+];
+
+/// Bundled programs that draw, with the keys they are given.
+const BUNDLED: [(&str, &[KeyEvent]); 4] = [
+    ("controller", &TYPING),
+    ("drawing01", &[]),
+    ("drawing02", &[]),
+    ("drawing03", &[]),
+];
+
+/// Test ROMs of Timendus' suite, and the platform choice they read from 0x1FF, if any.
+type TimendusRom = (&'static str, Option<fn(Variant) -> u8>);
+
+const TIMENDUS: [TimendusRom; 6] = [
+    ("1-chip8-logo.ch8", None),
+    ("2-ibm-logo.ch8", None),
+    ("3-corax+.ch8", None),
+    ("4-flags.ch8", None),
+    ("5-quirks.ch8", Some(platform)),
+    // Waits in its menu for a key, which no test presses
+    ("6-keypad.ch8", None),
+];
+
+fn platform(variant: Variant) -> u8 {
+    match variant {
+        Variant::Chip8 => 1,
+        Variant::Schip => 2,
+        Variant::Xochip => 3,
+    }
+}
+
+#[test]
+fn bundled_programs_match_their_golden_images() {
+    for (name, keys) in BUNDLED.iter() {
+        let program = fs::read(format!("programs/{}.c8", name)).unwrap();
+
+        for variant in VARIANTS.iter() {
+            let display = run(&program, *variant, None, keys);
+
+            check(
+                name,
+                *variant,
+                &display,
+                Path::new("tests/fixtures/programs"),
+            );
+        }
+    }
+}
+
+#[test]
+fn suite_roms_match_their_golden_images() {
+    let keypad = [
+        (0, suite::KEY, true),
+        (suite::KEY_RELEASE, suite::KEY, false),
+    ];
+    let roms: [(&str, Vec<u8>, &[KeyEvent]); 4] = [
+        ("opcodes", suite::opcodes(), &[]),
+        ("flags", suite::flags(), &[]),
+        ("quirks", suite::quirks(), &[]),
+        ("keypad", suite::keypad(), &keypad),
+    ];
+
+    for (name, program, keys) in roms.iter() {
+        for variant in VARIANTS.iter() {
+            let display = run(program, *variant, None, keys);
+
+            check(name, *variant, &display, Path::new("tests/fixtures/suite"));
+        }
+    }
+}
+
+#[test]
+#[ignore = "needs Timendus' test ROMs in the directory named by CHIPEYTE_TEST_ROMS"]
+fn timendus_roms_match_their_golden_images() {
+    let directory = env::var_os("CHIPEYTE_TEST_ROMS")
+        .expect("CHIPEYTE_TEST_ROMS must name the directory holding Timendus' test ROMs");
+
+    for (name, choice) in TIMENDUS.iter() {
+        let rom = Path::new(&directory).join(name);
+        let program =
+            fs::read(&rom).unwrap_or_else(|e| panic!("Cannot read {}: {}", rom.display(), e));
+        let name = rom.file_stem().unwrap().to_string_lossy();
+
+        for variant in VARIANTS.iter() {
+            let choice = choice.map(|choose| choose(*variant));
+            let display = run(&program, *variant, choice, &[]);
+
+            check(
+                &name,
+                *variant,
+                &display,
+                Path::new("tests/fixtures/timendus"),
+            );
+        }
+    }
+}
+
+/// Compares the display a ROM left under the quirks of the variant with its golden image.
+fn check(name: &str, variant: Variant, display: &str, fixtures: &Path) {
+    let golden = fixtures.join(format!("{}.{}.txt", name, variant));
+
+    let expected = fs::read_to_string(&golden);
+
+    if env::var_os("CHIPEYTE_BLESS").is_some() {
+        // Changed images are reported so that they are reviewed rather than accepted unseen
+        match &expected {
+            Ok(expected) if expected == display => return,
+            Ok(_) => eprintln!("Updated {}", golden.display()),
+            Err(_) => eprintln!("Recorded {}", golden.display()),
+        }
+
+        fs::create_dir_all(fixtures).unwrap();
+        fs::write(&golden, display).unwrap();
+        return;
+    }
+
+    match expected {
+        Ok(expected) => assert!(
+            expected == display,
+            "{} under {} quirks differs from {}:\n{}",
+            name,
+            variant,
+            golden.display(),
+            display
+        ),
+        Err(_) => panic!(
+            "No golden image {} for {}, run with CHIPEYTE_BLESS=1 to record it:\n{}",
+            golden.display(),
+            name,
+            display
+        ),
+    }
+}
+
+fn run(program: &[u8], variant: Variant, choice: Option<u8>, keys: &[KeyEvent]) -> String {
+    let mut interpreter = ChipeyteInterpreter::new(Config {
+        instructions_per_frame: Some(INSTRUCTIONS_PER_FRAME),
+        seed: Some(1),
+        quirks: variant.quirks(),
+        ..Config::default()
+    });
+    let mut controller = Controller::new();
+    interpreter.load(program);
+
+    if let Some(choice) = choice {
+        interpreter.poke(0x1ff, &[choice]);
+    }
+
+    for frame in 0..FRAMES {
+        for (_, key, pressed) in keys.iter().filter(|(at, _, _)| *at == frame) {
+            if *pressed {
+                controller.press_key(*key);
+            } else {
+                controller.release_key(*key);
+            }
+        }
+
+        if interpreter.run_frame(&mut controller).unwrap() == Status::Halted {
+            break;
+        }
+    }
+
+    image(interpreter.frame_buffer())
+}
+
+/// The display as rows of `#` for lit and `.` for dark pixels, as printed by `--headless`.
+fn image(frame_buffer: &FrameBuffer) -> String {
+    (0..frame_buffer.height())
+        .map(|y| {
+            (0..frame_buffer.width())
+                .map(|x| if frame_buffer.is_lit(x, y) { '#' } else { '.' })
+                .chain(Some('\n'))
+                .collect::<String>()
+        })
+        .collect()
+}
@@ -0,0 +1,269 @@
+//! Test ROMs in the style of Timendus' CHIP-8 test suite, assembled here so that what they check
+//! can be read next to their golden images.
+//!
+//! Each check runs a few instructions, compares a register with the value the original COSMAC VIP
+//! interpreter leaves in it and draws a tick or a cross, ten to a row. A variant that behaves
+//! differently shows a cross where the VIP shows a tick.
+
+const ORIGIN: u16 = 0x200;
+/// Sprites stored after the jump at the start of every ROM.
+const TICK: u16 = 0x202;
+const CROSS: u16 = 0x207;
+const LINE: u16 = 0x20c;
+/// Memory the checks store to, past the end of every ROM.
+const SCRATCH: u16 = 0x400;
+const COLUMNS: u32 = 10;
+
+/// A ROM under construction.
+struct Rom {
+    bytes: Vec<u8>,
+    checks: u32,
+}
+
+impl Rom {
+    fn new() -> Rom {
+        let mut rom = Rom {
+            bytes: Vec::new(),
+            checks: 0,
+        };
+
+        rom.emit(&[0x120e]);
+        rom.bytes.extend(&[0x08, 0x10, 0xa0, 0x40, 0x00]);
+        rom.bytes.extend(&[0x88, 0x50, 0x20, 0x50, 0x88]);
+        rom.bytes.extend(&[0xff, 0x00]);
+        // VD and VE hold the position of the next mark
+        rom.emit(&[0x6d01, 0x6e01]);
+        rom
+    }
+
+    fn address(&self) -> u16 {
+        ORIGIN + self.bytes.len() as u16
+    }
+
+    fn emit(&mut self, opcodes: &[u16]) {
+        for opcode in opcodes {
+            self.bytes.extend(&opcode.to_be_bytes());
+        }
+    }
+
+    /// Runs `setup` and marks whether register `x` then holds `expected`. The setup may use every
+    /// register but VD and VE.
+    fn check(&mut self, setup: &[u16], x: u8, expected: u8) {
+        let x = x as u16;
+
+        self.emit(setup);
+        self.emit(&[
+            0xa000 | CROSS,
+            0x4000 | x << 8 | expected as u16,
+            0xa000 | TICK,
+            0xdde5,
+            0x7d06,
+        ]);
+        self.checks += 1;
+
+        if self.checks.is_multiple_of(COLUMNS) {
+            self.emit(&[0x6d01, 0x7e06]);
+        }
+    }
+
+    /// Ends the ROM with a jump to itself.
+    fn finish(mut self) -> Vec<u8> {
+        let end = self.address();
+
+        self.emit(&[0x1000 | end]);
+        self.bytes
+    }
+}
+
+/// The result registers of every Chip-8 operation.
+pub fn opcodes() -> Vec<u8> {
+    let mut rom = Rom::new();
+
+    rom.check(&[0x602a], 0, 0x2a);
+    rom.check(&[0x6005, 0x7003], 0, 8);
+    rom.check(&[0x6107, 0x8010], 0, 7);
+    rom.check(&[0x600c, 0x6103, 0x8011], 0, 0x0f);
+    rom.check(&[0x600c, 0x6106, 0x8012], 0, 0x04);
+    rom.check(&[0x600c, 0x6106, 0x8013], 0, 0x0a);
+    rom.check(&[0x6005, 0x6103, 0x8014], 0, 8);
+    rom.check(&[0x6009, 0x6103, 0x8015], 0, 6);
+    rom.check(&[0x6003, 0x6109, 0x8017], 0, 6);
+    // Shifting a register into itself, which every variant agrees on
+    rom.check(&[0x6006, 0x8006], 0, 3);
+    rom.check(&[0x6006, 0x800e], 0, 0x0c);
+    // Skips over LD V0, 0
+    rom.check(&[0x6005, 0x3005, 0x6000], 0, 5);
+    rom.check(&[0x6005, 0x3006, 0x6000], 0, 0);
+    rom.check(&[0x6005, 0x4006, 0x6000], 0, 5);
+    rom.check(&[0x6005, 0x6105, 0x5010, 0x6000], 0, 5);
+    rom.check(&[0x6005, 0x6106, 0x9010, 0x6000], 0, 5);
+    // 156 in decimal digits
+    rom.check(&[0x609c, 0xa000 | SCRATCH, 0xf033, 0xf265], 1, 5);
+    rom.check(&[0x609c, 0xa000 | SCRATCH, 0xf033, 0xf265], 2, 6);
+    rom.check(
+        &[
+            0x6011,
+            0x6122,
+            0xa000 | SCRATCH,
+            0xf155,
+            0x6000,
+            0x6100,
+            0xa000 | SCRATCH,
+            0xf165,
+        ],
+        1,
+        0x22,
+    );
+    rom.check(
+        &[
+            0x6077,
+            0xa000 | (SCRATCH + 5),
+            0xf055,
+            0xa000 | SCRATCH,
+            0x6005,
+            0xf01e,
+            0xf065,
+        ],
+        0,
+        0x77,
+    );
+    // The first row of the font's 0
+    rom.check(&[0x6000, 0xf029, 0xf065], 0, 0xf0);
+    rom.check(&[0x6005, 0xf015, 0xf007], 0, 5);
+    rom.check(&[0x6055, 0xc000], 0, 0);
+
+    let start = rom.address();
+    // Jumps over LD V0, 0
+    rom.check(&[0x6001, 0x1000 | (start + 6), 0x6000], 0, 1);
+
+    let start = rom.address();
+    // Jumps over a subroutine setting V0 and then calls it
+    rom.check(
+        &[
+            0x1000 | (start + 6),
+            0x6042,
+            0x00ee,
+            0x6000,
+            0x2000 | (start + 2),
+        ],
+        0,
+        0x42,
+    );
+
+    rom.finish()
+}
+
+/// The carries, borrows and shifted out bits left in VF, and sprite collisions.
+pub fn flags() -> Vec<u8> {
+    let mut rom = Rom::new();
+
+    rom.check(&[0x60ff, 0x6102, 0x8014], 0xf, 1);
+    rom.check(&[0x60ff, 0x6102, 0x8014], 0, 1);
+    rom.check(&[0x6001, 0x6102, 0x8014], 0xf, 0);
+    rom.check(&[0x6005, 0x6103, 0x8015], 0xf, 1);
+    rom.check(&[0x6003, 0x6105, 0x8015], 0xf, 0);
+    rom.check(&[0x6003, 0x6105, 0x8017], 0xf, 1);
+    rom.check(&[0x6005, 0x6103, 0x8017], 0xf, 0);
+    rom.check(&[0x6005, 0x8006], 0xf, 1);
+    rom.check(&[0x6004, 0x8006], 0xf, 0);
+    rom.check(&[0x6081, 0x800e], 0xf, 1);
+    rom.check(&[0x6001, 0x800e], 0xf, 0);
+    // The flag wins when VF also takes the result
+    rom.check(&[0x6fff, 0x6102, 0x8f14], 0xf, 1);
+    rom.check(&[0x6f03, 0x6105, 0x8f15], 0xf, 0);
+    // Drawing a line in the bottom right corner, then erasing it
+    rom.check(&[0xa000 | LINE, 0x6338, 0x641f, 0xd341], 0xf, 0);
+    rom.check(&[0xa000 | LINE, 0x6338, 0x641f, 0xd341], 0xf, 1);
+
+    rom.finish()
+}
+
+/// The behaviours in `Quirks`, with the values of the COSMAC VIP.
+pub fn quirks() -> Vec<u8> {
+    let mut rom = Rom::new();
+
+    // Bnnn with V0 = 0 and V2 = 2 lands on the first or second ADD V1, 1
+    let start = rom.address();
+    rom.check(
+        &[
+            0x6000,
+            0x6202,
+            0x6100,
+            0xb000 | (start + 10),
+            0x7110,
+            0x7101,
+            0x7101,
+        ],
+        1,
+        2,
+    );
+
+    for opcode in [0x8011, 0x8012, 0x8013].iter() {
+        rom.check(&[0x6f05, 0x6001, 0x6102, *opcode], 0xf, 0);
+    }
+
+    // Stores 1 and then 0x55 without setting I in between
+    rom.check(
+        &[
+            0xa000 | (SCRATCH + 0x10),
+            0x6001,
+            0xf055,
+            0x6055,
+            0xf055,
+            0xa000 | (SCRATCH + 0x10),
+            0xf165,
+        ],
+        0,
+        1,
+    );
+
+    rom.check(&[0x6001, 0x6104, 0x8016], 0, 2);
+    rom.check(&[0x6001, 0x6104, 0x801e], 0, 8);
+
+    // A line at the right edge collides with one at the left edge when it wraps around
+    rom.check(
+        &[0x6000, 0x611f, 0xa000 | LINE, 0xd011, 0x603c, 0xd011],
+        0xf,
+        0,
+    );
+    rom.emit(&[0xa000 | LINE, 0xd011, 0x6000, 0xd011]);
+
+    // Four sprites take four frames when waiting for the vertical blank
+    rom.check(
+        &[
+            0x6002,
+            0xf015,
+            0xa000 | LINE,
+            0x6138,
+            0x621f,
+            0xd121,
+            0xd121,
+            0xd121,
+            0xd121,
+            0xf007,
+        ],
+        0,
+        0,
+    );
+
+    rom.finish()
+}
+
+/// Key to hold from the start of `keypad` until `KEY_RELEASE`.
+pub const KEY: u8 = 5;
+pub const KEY_RELEASE: u32 = 30;
+
+/// Key skips with a key held and not, and the wait for a key.
+pub fn keypad() -> Vec<u8> {
+    let mut rom = Rom::new();
+
+    rom.check(&[0x6100, 0x6003, 0xe0a1, 0x6101], 1, 0);
+    rom.check(&[0x6100, 0x6003, 0xe09e, 0x6101], 1, 1);
+    rom.check(&[0x6100, 0x6005, 0xe09e, 0x6101], 1, 0);
+    rom.check(&[0x6100, 0x6005, 0xe0a1, 0x6101], 1, 1);
+    // Waits until the held key is released
+    rom.check(&[0x6000, 0xf00a], 0, KEY);
+    rom.check(&[0x6100, 0x6005, 0xe0a1, 0x6101], 1, 0);
+
+    rom.finish()
+}